@@ -1,5 +1,6 @@
 use std::{path::Display, str::FromStr};
 
+use crate::debounce::{DebounceConfig, StateDebouncer};
 pub use battery_lib::State as ExternalBatteryState;
 use battery_lib::{
     Manager,
@@ -50,7 +51,7 @@ impl std::fmt::Display for State {
     }
 }
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
-struct Identifier {
+pub struct Identifier {
     //供应商
     pub vendor: Option<String>,
     //模式
@@ -75,10 +76,14 @@ pub struct Status {
     identifier: Identifier,
     //序号
     index: u16,
-    //时间戳
+    //时间戳(毫秒)
     pub timestamp: i64,
-    //状态
+    //去抖后的状态
     pub state: State,
+    //驱动报告的原始状态
+    pub raw_state: State,
+    #[serde(skip)]
+    debouncer: StateDebouncer,
     //温度
     pub temperature: Option<f32>,
     //循环次数
@@ -112,9 +117,11 @@ impl<'a> Default for Status {
             },
             index: 0,
             state_changed: false,
-            timestamp: Utc::now().timestamp(),
+            timestamp: Utc::now().timestamp_millis(),
 
             state: State(ExternalBatteryState::Unknown),
+            raw_state: State(ExternalBatteryState::Unknown),
+            debouncer: StateDebouncer::default(),
             temperature: None,
             cycle_count: None,
             percentage: 0.0,
@@ -130,6 +137,23 @@ impl<'a> Default for Status {
     }
 }
 impl Status {
+    pub fn identifier(&self) -> &Identifier {
+        &self.identifier
+    }
+    //区分电池的标识，由供应商、型号、序列号组成
+    pub fn key(&self) -> String {
+        [
+            &self.identifier.vendor,
+            &self.identifier.model,
+            &self.identifier.serial_number,
+        ]
+        .map(|x| x.as_deref().unwrap_or("-"))
+        .join("/")
+    }
+    //修改去抖参数，保留当前的稳定状态
+    pub fn set_debounce(&mut self, config: DebounceConfig) {
+        self.debouncer.config = config;
+    }
     fn refresh(&mut self) {
         let manager = Manager::new().unwrap();
         let batteries = manager.batteries();
@@ -148,15 +172,18 @@ impl Status {
         if let Ok(battery) = o.unwrap() {
             let status = self;
 
-            let new_state = State(battery.state());
-            status.state_changed =
-                status.state.0 != ExternalBatteryState::Unknown && status.state != new_state;
-            status.state = new_state;
-            status.timestamp = Utc::now().timestamp();
+            status.timestamp = Utc::now().timestamp_millis();
             status.percentage = battery.state_of_charge().get::<ratio>();
+            //固件在满电附近会在Full、Charging、Unknown之间抖动，状态稳定后才切换
+            status.raw_state = State(battery.state());
+            status.state_changed = status
+                .debouncer
+                .observe(status.raw_state, status.percentage, status.timestamp)
+                .is_some();
+            status.state = status.debouncer.stable().unwrap_or(status.raw_state);
             status.voltage = battery.voltage().get::<volt>();
             status.state_of_health = battery.state_of_health().get::<ratio>();
-            status.energy_rate = match status.state {
+            status.energy_rate = match status.raw_state {
                 State(ExternalBatteryState::Empty) => -battery.energy_rate().get::<watt>(),
                 State(ExternalBatteryState::Discharging) => -battery.energy_rate().get::<watt>(),
                 State(ExternalBatteryState::Full) => 0.0,
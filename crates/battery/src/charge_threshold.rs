@@ -0,0 +1,28 @@
+//设置充电上限，目前仅支持linux的sysfs接口，windows下需要厂商驱动
+#[cfg(target_os = "linux")]
+pub fn set_charge_threshold(percentage: u8) -> Result<(), String> {
+    if percentage == 0 || percentage > 100 {
+        return Err(format!("invalid charge threshold({})", percentage));
+    }
+    let dir = std::fs::read_dir("/sys/class/power_supply").map_err(|e| e.to_string())?;
+    let mut count = 0;
+    for entry in dir.flatten() {
+        let path = entry.path().join("charge_control_end_threshold");
+        if entry.file_name().to_string_lossy().starts_with("BAT") && path.exists() {
+            std::fs::write(&path, percentage.to_string()).map_err(|e| e.to_string())?;
+            count += 1;
+        }
+    }
+    if count == 0 {
+        Err("not support".to_string())
+    } else {
+        Ok(())
+    }
+}
+#[cfg(not(target_os = "linux"))]
+pub fn set_charge_threshold(percentage: u8) -> Result<(), String> {
+    if percentage == 0 || percentage > 100 {
+        return Err(format!("invalid charge threshold({})", percentage));
+    }
+    Err("not support".to_string())
+}
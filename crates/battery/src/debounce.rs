@@ -0,0 +1,86 @@
+use crate::battery_status::{ExternalBatteryState, State};
+use serde::{Deserialize, Serialize};
+
+//电池状态切换的滞后参数
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct DebounceConfig {
+    //新状态需要持续的最短时间(毫秒)，0表示立即切换
+    pub min_dwell_ms: i64,
+    //忽略Unknown读数，保持原状态
+    pub ignore_unknown: bool,
+    //电量不低于此值(0-1)时Full与Charging视为同一状态，None表示不合并
+    pub merge_full_above: Option<f32>,
+}
+impl Default for DebounceConfig {
+    fn default() -> Self {
+        Self {
+            min_dwell_ms: 30_000,
+            ignore_unknown: true,
+            merge_full_above: Some(0.95),
+        }
+    }
+}
+//状态机：原始状态持续min_dwell_ms后才成为稳定状态
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StateDebouncer {
+    pub config: DebounceConfig,
+    stable: Option<State>,
+    //候选状态及其开始时间
+    pending: Option<(State, i64)>,
+}
+fn is_top(state: State) -> bool {
+    matches!(
+        state.0,
+        ExternalBatteryState::Full | ExternalBatteryState::Charging
+    )
+}
+impl StateDebouncer {
+    pub fn new(config: DebounceConfig) -> Self {
+        Self {
+            config,
+            stable: None,
+            pending: None,
+        }
+    }
+    pub fn stable(&self) -> Option<State> {
+        self.stable
+    }
+    //原始状态是否与当前稳定状态等价
+    fn same_as_stable(&self, raw: State, percentage: f32) -> bool {
+        match self.stable {
+            Some(stable) if stable == raw => true,
+            Some(stable) => match self.config.merge_full_above {
+                Some(above) => percentage >= above && is_top(stable) && is_top(raw),
+                None => false,
+            },
+            None => false,
+        }
+    }
+    //输入一次原始读数，稳定状态发生切换时返回新状态，首次读数只作为初始状态
+    pub fn observe(&mut self, raw: State, percentage: f32, now: i64) -> Option<State> {
+        if self.config.ignore_unknown && raw.0 == ExternalBatteryState::Unknown {
+            return None;
+        }
+        if self.stable.is_none() {
+            self.stable = Some(raw);
+            return None;
+        }
+        if self.same_as_stable(raw, percentage) {
+            self.pending = None;
+            return None;
+        }
+        let since = match self.pending {
+            Some((state, since)) if state == raw => since,
+            _ => {
+                self.pending = Some((raw, now));
+                now
+            }
+        };
+        if now - since < self.config.min_dwell_ms {
+            return None;
+        }
+        self.pending = None;
+        self.stable = Some(raw);
+        self.stable
+    }
+}
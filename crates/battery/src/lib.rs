@@ -1,5 +1,9 @@
 pub mod battery_status;
+pub mod charge_threshold;
+pub mod debounce;
 pub use battery_status::*;
+pub use charge_threshold::*;
+pub use debounce::*;
 #[cfg(test)]
 mod tests {
     use crate::battery_status::*;
@@ -25,4 +29,64 @@ mod tests {
         assert_eq!(status, status1);
         assert_eq!(status.timestamp, status1.timestamp);
     }
+    #[test]
+    fn debounce_flicker() {
+        let state = |x| State(x);
+        let mut debouncer = crate::StateDebouncer::new(crate::DebounceConfig {
+            min_dwell_ms: 30_000,
+            ignore_unknown: true,
+            merge_full_above: Some(0.95),
+        });
+        assert_eq!(
+            debouncer.observe(state(ExternalBatteryState::Charging), 0.9, 0),
+            None
+        );
+        //满电附近Full、Charging、Unknown交替出现不切换
+        for (i, raw) in [
+            ExternalBatteryState::Full,
+            ExternalBatteryState::Unknown,
+            ExternalBatteryState::Charging,
+            ExternalBatteryState::Full,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            assert_eq!(debouncer.observe(state(raw), 0.99, i as i64 * 60_000), None);
+        }
+        assert_eq!(
+            debouncer.stable(),
+            Some(state(ExternalBatteryState::Charging))
+        );
+        //放电不足最短持续时间时回到原状态
+        assert_eq!(
+            debouncer.observe(state(ExternalBatteryState::Discharging), 0.99, 300_000),
+            None
+        );
+        assert_eq!(
+            debouncer.observe(state(ExternalBatteryState::Charging), 0.99, 310_000),
+            None
+        );
+        assert_eq!(
+            debouncer.observe(state(ExternalBatteryState::Discharging), 0.99, 320_000),
+            None
+        );
+        assert_eq!(
+            debouncer.observe(state(ExternalBatteryState::Discharging), 0.98, 340_000),
+            None
+        );
+        assert_eq!(
+            debouncer.observe(state(ExternalBatteryState::Discharging), 0.98, 350_000),
+            Some(state(ExternalBatteryState::Discharging))
+        );
+        //低电量时Full与Charging不合并
+        let mut debouncer = crate::StateDebouncer::new(crate::DebounceConfig {
+            min_dwell_ms: 0,
+            ..Default::default()
+        });
+        debouncer.observe(state(ExternalBatteryState::Charging), 0.5, 0);
+        assert_eq!(
+            debouncer.observe(state(ExternalBatteryState::Full), 0.5, 1),
+            Some(state(ExternalBatteryState::Full))
+        );
+    }
 }
@@ -0,0 +1,25 @@
+use persis::merge_micro_segments;
+use sea_orm::Database;
+use sea_orm_migration::MigratorTrait;
+
+//清理已有数据库中状态抖动产生的短历史段
+//cargo run --example merge_micro_segments -- <history.db> [min_duration_secs]
+#[tokio::main]
+async fn main() {
+    let mut args = std::env::args().skip(1);
+    let path = args
+        .next()
+        .expect("usage: merge_micro_segments <history.db> [min_duration_secs]");
+    let min_duration_secs: i64 = args.next().map_or(30, |x| x.parse().unwrap());
+    let db = Database::connect(format!("sqlite://{}?mode=rw", path))
+        .await
+        .unwrap();
+    migration::Migrator::up(&db, None).await.unwrap();
+    let removed = merge_micro_segments(&db, min_duration_secs * 1000)
+        .await
+        .unwrap();
+    println!(
+        "merged {} segments shorter than {}s",
+        removed, min_duration_secs
+    );
+}
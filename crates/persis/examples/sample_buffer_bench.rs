@@ -0,0 +1,89 @@
+use persis::down_sample::{BatterySample, DownSampleParams, down_sample};
+use persis::{SampleBuffer, memory_battery_status};
+use sea_orm::*;
+use sea_orm_migration::MigratorTrait;
+use std::time::Instant;
+
+//模拟运行一段时间：每秒写入一个样本，每10秒降采样一次并清理1分钟前的样本
+const SECONDS: i64 = 3600;
+const START: i64 = 1_743_800_000_000;
+
+fn sample(i: i64) -> memory_battery_status::Model {
+    memory_battery_status::Model {
+        id: 0,
+        timestamp: START + i * 1000,
+        state: "discharging".to_string(),
+        percentage: 80.0 - i as f32 / 100.0,
+        energy_rate: -8.0,
+        voltage: 12.0,
+        cpu_load: (i % 10) as f32 / 10.0,
+        temperature: Some(40.0),
+        cycle_count: Some(100),
+        time_to_empty_secs: Some(3600 - i),
+        time_to_full_secs: None,
+    }
+}
+async fn bench_sqlite() -> usize {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    migration::Migrator::up(&db, None).await.unwrap();
+    let mut rows = 0;
+    for i in 0..SECONDS {
+        let mut model = sample(i).into_active_model();
+        model.id = NotSet;
+        model.insert(&db).await.unwrap();
+        if i % 10 == 9 {
+            let now = START + i * 1000;
+            memory_battery_status::Entity::delete_many()
+                .filter(memory_battery_status::Column::Timestamp.lt(now - 60_000))
+                .exec(&db)
+                .await
+                .unwrap();
+            let res: Vec<BatterySample> = down_sample(
+                &db,
+                &DownSampleParams {
+                    start_time: now - 10_000,
+                    end_time: now,
+                    interval: 2000,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+            rows += res.len();
+        }
+    }
+    rows
+}
+fn bench_buffer() -> usize {
+    let mut buffer = SampleBuffer::new(120);
+    let mut rows = 0;
+    for i in 0..SECONDS {
+        buffer.push(sample(i));
+        if i % 10 == 9 {
+            let now = START + i * 1000;
+            buffer.clean(now - 60_000);
+            rows += buffer.down_sample(now - 10_000, now, 2000).len();
+        }
+    }
+    rows
+}
+#[tokio::main]
+async fn main() {
+    let start = Instant::now();
+    let sqlite_rows = bench_sqlite().await;
+    let sqlite_elapsed = start.elapsed();
+    let start = Instant::now();
+    let buffer_rows = bench_buffer();
+    let buffer_elapsed = start.elapsed();
+    assert_eq!(sqlite_rows, buffer_rows);
+    println!(
+        "sqlite::memory: {:?} ({:?}/tick)",
+        sqlite_elapsed,
+        sqlite_elapsed / SECONDS as u32
+    );
+    println!(
+        "ring buffer:    {:?} ({:?}/tick)",
+        buffer_elapsed,
+        buffer_elapsed / SECONDS as u32
+    );
+}
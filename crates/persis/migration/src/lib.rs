@@ -2,6 +2,17 @@ pub use sea_orm_migration::prelude::*;
 
 //mod m20220101_000001_create_table;
 mod m20250311_150000_init;
+mod m20250401_000000_power;
+mod m20250402_000000_power_audit;
+mod m20250403_000000_experiment;
+mod m20250404_000000_rollup;
+mod m20250405_000000_battery_extra;
+mod m20250406_000000_surrogate_key;
+mod m20250407_000000_sleep_session;
+mod m20250408_000000_battery_cycles;
+mod m20250409_000000_battery_stress;
+mod m20250410_000000_battery_health;
+mod m20250411_000000_power_audit_limit;
 
 pub struct Migrator;
 
@@ -11,6 +22,17 @@ impl MigratorTrait for Migrator {
         vec![
             //Box::new(m20220101_000001_create_table::Migration)
             Box::new(m20250311_150000_init::Migration),
+            Box::new(m20250401_000000_power::Migration),
+            Box::new(m20250402_000000_power_audit::Migration),
+            Box::new(m20250403_000000_experiment::Migration),
+            Box::new(m20250404_000000_rollup::Migration),
+            Box::new(m20250405_000000_battery_extra::Migration),
+            Box::new(m20250406_000000_surrogate_key::Migration),
+            Box::new(m20250407_000000_sleep_session::Migration),
+            Box::new(m20250408_000000_battery_cycles::Migration),
+            Box::new(m20250409_000000_battery_stress::Migration),
+            Box::new(m20250410_000000_battery_health::Migration),
+            Box::new(m20250411_000000_power_audit_limit::Migration),
         ]
     }
 }
@@ -0,0 +1,121 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(MemoryPowerStatus::Table)
+                    .if_not_exists()
+                    .col(pk_auto(MemoryPowerStatus::Id))
+                    .col(big_integer(MemoryPowerStatus::Timestamp))
+                    .col(float(MemoryPowerStatus::StapmLimit))
+                    .col(float(MemoryPowerStatus::StapmValue))
+                    .col(float(MemoryPowerStatus::SlowLimit))
+                    .col(float(MemoryPowerStatus::SlowValue))
+                    .col(float(MemoryPowerStatus::FastLimit))
+                    .col(float(MemoryPowerStatus::FastValue))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .table(MemoryPowerStatus::Table)
+                    .name("idx_MemoryPowerStatus_timestamp")
+                    .col(MemoryPowerStatus::Timestamp)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(PowerRealtime::Table)
+                    .if_not_exists()
+                    .col(big_integer(PowerRealtime::Timestamp).primary_key())
+                    .col(float(PowerRealtime::StapmLimit))
+                    .col(float(PowerRealtime::StapmValue))
+                    .col(float(PowerRealtime::SlowLimit))
+                    .col(float(PowerRealtime::SlowValue))
+                    .col(float(PowerRealtime::FastLimit))
+                    .col(float(PowerRealtime::FastValue))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(PowerOneMinutes::Table)
+                    .if_not_exists()
+                    .col(big_integer(PowerOneMinutes::Timestamp).primary_key())
+                    .col(float(PowerOneMinutes::StapmLimit))
+                    .col(float(PowerOneMinutes::StapmValue))
+                    .col(float(PowerOneMinutes::SlowLimit))
+                    .col(float(PowerOneMinutes::SlowValue))
+                    .col(float(PowerOneMinutes::FastLimit))
+                    .col(float(PowerOneMinutes::FastValue))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .table(MemoryPowerStatus::Table)
+                    .name("idx_MemoryPowerStatus_timestamp")
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(MemoryPowerStatus::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(PowerRealtime::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(PowerOneMinutes::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+#[derive(DeriveIden)]
+enum MemoryPowerStatus {
+    Table,
+    Id,
+    Timestamp,
+    StapmLimit,
+    StapmValue,
+    SlowLimit,
+    SlowValue,
+    FastLimit,
+    FastValue,
+}
+#[derive(DeriveIden)]
+enum PowerRealtime {
+    Table,
+    Timestamp,
+    StapmLimit,
+    StapmValue,
+    SlowLimit,
+    SlowValue,
+    FastLimit,
+    FastValue,
+}
+#[derive(DeriveIden)]
+enum PowerOneMinutes {
+    Table,
+    Timestamp,
+    StapmLimit,
+    StapmValue,
+    SlowLimit,
+    SlowValue,
+    FastLimit,
+    FastValue,
+}
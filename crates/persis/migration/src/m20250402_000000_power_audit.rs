@@ -0,0 +1,77 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PowerLimitAudit::Table)
+                    .if_not_exists()
+                    .col(pk_auto(PowerLimitAudit::Id))
+                    .col(big_integer(PowerLimitAudit::Timestamp))
+                    .col(string(PowerLimitAudit::Source))
+                    .col(ColumnDef::new(PowerLimitAudit::Detail).string().null())
+                    .col(float(PowerLimitAudit::BeforeStapm))
+                    .col(float(PowerLimitAudit::BeforeSlow))
+                    .col(float(PowerLimitAudit::BeforeFast))
+                    .col(float(PowerLimitAudit::RequestStapm))
+                    .col(float(PowerLimitAudit::RequestSlow))
+                    .col(float(PowerLimitAudit::RequestFast))
+                    .col(ColumnDef::new(PowerLimitAudit::AfterStapm).float().null())
+                    .col(ColumnDef::new(PowerLimitAudit::AfterSlow).float().null())
+                    .col(ColumnDef::new(PowerLimitAudit::AfterFast).float().null())
+                    .col(boolean(PowerLimitAudit::Success))
+                    .col(ColumnDef::new(PowerLimitAudit::Error).string().null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .table(PowerLimitAudit::Table)
+                    .name("idx_PowerLimitAudit_timestamp")
+                    .col(PowerLimitAudit::Timestamp)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .table(PowerLimitAudit::Table)
+                    .name("idx_PowerLimitAudit_timestamp")
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(PowerLimitAudit::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+#[derive(DeriveIden)]
+enum PowerLimitAudit {
+    Table,
+    Id,
+    Timestamp,
+    Source,
+    Detail,
+    BeforeStapm,
+    BeforeSlow,
+    BeforeFast,
+    RequestStapm,
+    RequestSlow,
+    RequestFast,
+    AfterStapm,
+    AfterSlow,
+    AfterFast,
+    Success,
+    Error,
+}
@@ -0,0 +1,100 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PowerExperiment::Table)
+                    .if_not_exists()
+                    .col(pk_auto(PowerExperiment::Id))
+                    .col(big_integer(PowerExperiment::StartedAt))
+                    .col(big_integer(PowerExperiment::EndedAt))
+                    .col(integer(PowerExperiment::IntervalSecs))
+                    .col(integer(PowerExperiment::Rounds))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(PowerExperimentResult::Table)
+                    .if_not_exists()
+                    .col(pk_auto(PowerExperimentResult::Id))
+                    .col(integer(PowerExperimentResult::ExperimentId))
+                    .col(string(PowerExperimentResult::Preset))
+                    .col(string(PowerExperimentResult::Metric))
+                    .col(integer(PowerExperimentResult::Samples))
+                    .col(float(PowerExperimentResult::Mean))
+                    .col(float(PowerExperimentResult::StdDev))
+                    .col(float(PowerExperimentResult::CiLow))
+                    .col(float(PowerExperimentResult::CiHigh))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_PowerExperimentResult_experiment")
+                            .from(
+                                PowerExperimentResult::Table,
+                                PowerExperimentResult::ExperimentId,
+                            )
+                            .to(PowerExperiment::Table, PowerExperiment::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .table(PowerExperimentResult::Table)
+                    .name("idx_PowerExperimentResult_experiment_id")
+                    .col(PowerExperimentResult::ExperimentId)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .table(PowerExperimentResult::Table)
+                    .name("idx_PowerExperimentResult_experiment_id")
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(PowerExperimentResult::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(PowerExperiment::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+#[derive(DeriveIden)]
+enum PowerExperiment {
+    Table,
+    Id,
+    StartedAt,
+    EndedAt,
+    IntervalSecs,
+    Rounds,
+}
+#[derive(DeriveIden)]
+enum PowerExperimentResult {
+    Table,
+    Id,
+    ExperimentId,
+    Preset,
+    Metric,
+    Samples,
+    Mean,
+    StdDev,
+    CiLow,
+    CiHigh,
+}
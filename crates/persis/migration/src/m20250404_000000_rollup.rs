@@ -0,0 +1,121 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(BatteryOneHour::Table)
+                    .if_not_exists()
+                    .col(big_integer(BatteryOneHour::Timestamp).primary_key())
+                    .col(string(BatteryOneHour::State))
+                    .col(integer(BatteryOneHour::Samples))
+                    .col(float(BatteryOneHour::PercentageMin))
+                    .col(float(BatteryOneHour::PercentageMax))
+                    .col(float(BatteryOneHour::PercentageAvg))
+                    .col(float(BatteryOneHour::PercentageLast))
+                    .col(float(BatteryOneHour::EnergyRateMin))
+                    .col(float(BatteryOneHour::EnergyRateMax))
+                    .col(float(BatteryOneHour::EnergyRateAvg))
+                    .col(float(BatteryOneHour::EnergyRateLast))
+                    .col(float(BatteryOneHour::VoltageMin))
+                    .col(float(BatteryOneHour::VoltageMax))
+                    .col(float(BatteryOneHour::VoltageAvg))
+                    .col(float(BatteryOneHour::VoltageLast))
+                    .col(float(BatteryOneHour::CpuLoadMin))
+                    .col(float(BatteryOneHour::CpuLoadMax))
+                    .col(float(BatteryOneHour::CpuLoadAvg))
+                    .col(float(BatteryOneHour::CpuLoadLast))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(BatteryOneDay::Table)
+                    .if_not_exists()
+                    .col(big_integer(BatteryOneDay::Timestamp).primary_key())
+                    .col(string(BatteryOneDay::State))
+                    .col(integer(BatteryOneDay::Samples))
+                    .col(float(BatteryOneDay::PercentageMin))
+                    .col(float(BatteryOneDay::PercentageMax))
+                    .col(float(BatteryOneDay::PercentageAvg))
+                    .col(float(BatteryOneDay::PercentageLast))
+                    .col(float(BatteryOneDay::EnergyRateMin))
+                    .col(float(BatteryOneDay::EnergyRateMax))
+                    .col(float(BatteryOneDay::EnergyRateAvg))
+                    .col(float(BatteryOneDay::EnergyRateLast))
+                    .col(float(BatteryOneDay::VoltageMin))
+                    .col(float(BatteryOneDay::VoltageMax))
+                    .col(float(BatteryOneDay::VoltageAvg))
+                    .col(float(BatteryOneDay::VoltageLast))
+                    .col(float(BatteryOneDay::CpuLoadMin))
+                    .col(float(BatteryOneDay::CpuLoadMax))
+                    .col(float(BatteryOneDay::CpuLoadAvg))
+                    .col(float(BatteryOneDay::CpuLoadLast))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(BatteryOneHour::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(BatteryOneDay::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+#[derive(DeriveIden)]
+enum BatteryOneHour {
+    Table,
+    Timestamp,
+    State,
+    Samples,
+    PercentageMin,
+    PercentageMax,
+    PercentageAvg,
+    PercentageLast,
+    EnergyRateMin,
+    EnergyRateMax,
+    EnergyRateAvg,
+    EnergyRateLast,
+    VoltageMin,
+    VoltageMax,
+    VoltageAvg,
+    VoltageLast,
+    CpuLoadMin,
+    CpuLoadMax,
+    CpuLoadAvg,
+    CpuLoadLast,
+}
+#[derive(DeriveIden)]
+enum BatteryOneDay {
+    Table,
+    Timestamp,
+    State,
+    Samples,
+    PercentageMin,
+    PercentageMax,
+    PercentageAvg,
+    PercentageLast,
+    EnergyRateMin,
+    EnergyRateMax,
+    EnergyRateAvg,
+    EnergyRateLast,
+    VoltageMin,
+    VoltageMax,
+    VoltageAvg,
+    VoltageLast,
+    CpuLoadMin,
+    CpuLoadMax,
+    CpuLoadAvg,
+    CpuLoadLast,
+}
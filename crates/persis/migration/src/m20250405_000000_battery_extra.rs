@@ -0,0 +1,82 @@
+use sea_orm_migration::prelude::*;
+
+const TABLES: [&str; 4] = [
+    "memory_battery_status",
+    "battery_realtime",
+    "battery_one_minutes",
+    "battery_state_history",
+];
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+//旧数据没有这些读数，均允许为NULL
+fn columns() -> Vec<ColumnDef> {
+    vec![
+        ColumnDef::new(BatteryExtra::Temperature)
+            .float()
+            .null()
+            .to_owned(),
+        ColumnDef::new(BatteryExtra::CycleCount)
+            .integer()
+            .null()
+            .to_owned(),
+        ColumnDef::new(BatteryExtra::TimeToEmptySecs)
+            .big_integer()
+            .null()
+            .to_owned(),
+        ColumnDef::new(BatteryExtra::TimeToFullSecs)
+            .big_integer()
+            .null()
+            .to_owned(),
+    ]
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        //SQLite每条ALTER TABLE只能添加一列
+        for table in TABLES {
+            for mut column in columns() {
+                manager
+                    .alter_table(
+                        Table::alter()
+                            .table(Alias::new(table))
+                            .add_column(&mut column)
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for table in TABLES {
+            for column in [
+                BatteryExtra::Temperature,
+                BatteryExtra::CycleCount,
+                BatteryExtra::TimeToEmptySecs,
+                BatteryExtra::TimeToFullSecs,
+            ] {
+                manager
+                    .alter_table(
+                        Table::alter()
+                            .table(Alias::new(table))
+                            .drop_column(column)
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum BatteryExtra {
+    Temperature,
+    CycleCount,
+    TimeToEmptySecs,
+    TimeToFullSecs,
+}
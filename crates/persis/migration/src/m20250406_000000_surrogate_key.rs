@@ -0,0 +1,208 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+//以timestamp为主键的表，重建为自增id主键
+const REBUILD_TABLES: [&str; 3] = [
+    "battery_realtime",
+    "battery_one_minutes",
+    "battery_state_history",
+];
+//主键不变，只需转换时间单位的列，功率相关的表也统一为毫秒
+const SCALE_COLUMNS: [(&str, &str); 9] = [
+    ("memory_battery_status", "timestamp"),
+    ("battery_one_hour", "timestamp"),
+    ("battery_one_day", "timestamp"),
+    ("memory_power_status", "timestamp"),
+    ("power_realtime", "timestamp"),
+    ("power_one_minutes", "timestamp"),
+    ("power_limit_audit", "timestamp"),
+    ("power_experiment", "started_at"),
+    ("power_experiment", "ended_at"),
+];
+//秒转换为毫秒
+const SCALE: i64 = 1000;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+fn is_history(table: &str) -> bool {
+    table == "battery_state_history"
+}
+//surrogate为false时为原先以timestamp为主键的结构
+fn create_statement(table: &str, name: &str, surrogate: bool) -> TableCreateStatement {
+    let mut stmt = Table::create().table(Alias::new(name)).to_owned();
+    match surrogate {
+        true => stmt
+            .col(pk_auto(Battery::Id))
+            .col(big_integer(Battery::Timestamp)),
+        false => stmt.col(big_integer(Battery::Timestamp).primary_key()),
+    };
+    stmt.col(string(Battery::State));
+    if is_history(table) {
+        stmt.col(string_null(Battery::Prev))
+            .col(big_integer_null(Battery::EndAt))
+            .col(float(Battery::Capacity))
+            .col(float(Battery::FullCapacity))
+            .col(float(Battery::DesignCapacity));
+    }
+    stmt.col(float(Battery::Percentage));
+    if is_history(table) {
+        stmt.col(float(Battery::StateOfHealth));
+    }
+    stmt.col(float(Battery::EnergyRate))
+        .col(float(Battery::Voltage))
+        .col(float(Battery::CpuLoad))
+        .col(float_null(Battery::Temperature))
+        .col(integer_null(Battery::CycleCount))
+        .col(big_integer_null(Battery::TimeToEmptySecs))
+        .col(big_integer_null(Battery::TimeToFullSecs));
+    stmt
+}
+//除时间外原样复制的列
+fn copy_columns(table: &str) -> Vec<&'static str> {
+    let mut columns = vec!["state"];
+    if is_history(table) {
+        columns.extend(["prev", "capacity", "full_capacity", "design_capacity"]);
+        columns.push("state_of_health");
+    }
+    columns.extend([
+        "percentage",
+        "energy_rate",
+        "voltage",
+        "cpu_load",
+        "temperature",
+        "cycle_count",
+        "time_to_empty_secs",
+        "time_to_full_secs",
+    ]);
+    columns
+}
+//SQLite无法修改主键，新建表复制数据后替换原表，索引随原表删除后重新创建
+async fn rebuild(manager: &SchemaManager<'_>, table: &str, surrogate: bool) -> Result<(), DbErr> {
+    let db = manager.get_connection();
+    let new_table = format!("{table}_new");
+    manager
+        .create_table(create_statement(table, &new_table, surrogate))
+        .await?;
+    let mut times = vec!["timestamp"];
+    if is_history(table) {
+        times.push("end_at");
+    }
+    let scale = match surrogate {
+        true => format!("* {SCALE}"),
+        false => format!("/ {SCALE}"),
+    };
+    let columns = copy_columns(table);
+    let targets: Vec<String> = times
+        .iter()
+        .chain(columns.iter())
+        .map(|x| format!("\"{x}\""))
+        .collect();
+    let sources: Vec<String> = times
+        .iter()
+        .map(|x| format!("\"{x}\" {scale}"))
+        .chain(columns.iter().map(|x| format!("\"{x}\"")))
+        .collect();
+    //回滚时同一秒内的多条数据只保留第一条
+    db.execute_unprepared(&format!(
+        r#"INSERT {ignore} INTO "{new_table}" ({targets}) SELECT {sources} FROM "{table}" ORDER BY "timestamp""#,
+        ignore = match surrogate {
+            true => "",
+            false => "OR IGNORE",
+        },
+        targets = targets.join(", "),
+        sources = sources.join(", "),
+    ))
+    .await?;
+    manager
+        .drop_table(Table::drop().table(Alias::new(table)).to_owned())
+        .await?;
+    manager
+        .rename_table(
+            Table::rename()
+                .table(Alias::new(&new_table), Alias::new(table))
+                .to_owned(),
+        )
+        .await?;
+    if surrogate {
+        //降采样表按时间分组写入，时间需要唯一
+        let mut index = Index::create()
+            .table(Alias::new(table))
+            .name(format!("idx_{table}_timestamp"))
+            .col(Battery::Timestamp)
+            .to_owned();
+        if !is_history(table) {
+            index.unique();
+        }
+        manager.create_index(index).await?;
+    }
+    if is_history(table) {
+        for (name, column) in [
+            ("idx_BatteryStateHistory_state", Battery::State),
+            ("idx_BatteryStateHistory_prev", Battery::Prev),
+        ] {
+            manager
+                .create_index(
+                    Index::create()
+                        .table(Alias::new(table))
+                        .name(name)
+                        .col(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+    }
+    Ok(())
+}
+async fn scale(manager: &SchemaManager<'_>, surrogate: bool) -> Result<(), DbErr> {
+    let op = match surrogate {
+        true => "*",
+        false => "/",
+    };
+    for (table, column) in SCALE_COLUMNS {
+        manager
+            .get_connection()
+            .execute_unprepared(&format!(
+                r#"UPDATE "{table}" SET "{column}" = "{column}" {op} {SCALE}"#
+            ))
+            .await?;
+    }
+    Ok(())
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for table in REBUILD_TABLES {
+            rebuild(manager, table, true).await?;
+        }
+        scale(manager, true).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for table in REBUILD_TABLES {
+            rebuild(manager, table, false).await?;
+        }
+        scale(manager, false).await
+    }
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum Battery {
+    Id,
+    Timestamp,
+    State,
+    Prev,
+    EndAt,
+    Capacity,
+    FullCapacity,
+    DesignCapacity,
+    Percentage,
+    StateOfHealth,
+    EnergyRate,
+    Voltage,
+    CpuLoad,
+    Temperature,
+    CycleCount,
+    TimeToEmptySecs,
+    TimeToFullSecs,
+}
@@ -0,0 +1,67 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SleepSessions::Table)
+                    .if_not_exists()
+                    .col(pk_auto(SleepSessions::Id))
+                    .col(big_integer(SleepSessions::StartAt))
+                    .col(big_integer(SleepSessions::EndAt))
+                    .col(float(SleepSessions::StartPercentage))
+                    .col(float(SleepSessions::EndPercentage))
+                    .col(float(SleepSessions::StartCapacity))
+                    .col(float(SleepSessions::EndCapacity))
+                    .col(float(SleepSessions::FullCapacity))
+                    .col(float(SleepSessions::DrainRate))
+                    .col(float(SleepSessions::PercentageRate))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .table(SleepSessions::Table)
+                    .name("idx_SleepSessions_start_at")
+                    .col(SleepSessions::StartAt)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .table(SleepSessions::Table)
+                    .name("idx_SleepSessions_start_at")
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(SleepSessions::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+#[derive(DeriveIden)]
+enum SleepSessions {
+    Table,
+    Id,
+    StartAt,
+    EndAt,
+    StartPercentage,
+    EndPercentage,
+    StartCapacity,
+    EndCapacity,
+    FullCapacity,
+    DrainRate,
+    PercentageRate,
+}
@@ -0,0 +1,44 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(BatteryCycles::Table)
+                    .if_not_exists()
+                    .col(pk_auto(BatteryCycles::Id))
+                    .col(string_uniq(BatteryCycles::BatteryKey))
+                    .col(double(BatteryCycles::DischargedWh))
+                    .col(double(BatteryCycles::Cycles))
+                    .col(integer_null(BatteryCycles::DriverCycleCount))
+                    .col(float_null(BatteryCycles::LastCapacity))
+                    .col(big_integer(BatteryCycles::UpdatedAt))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(BatteryCycles::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+#[derive(DeriveIden)]
+enum BatteryCycles {
+    Table,
+    Id,
+    BatteryKey,
+    DischargedWh,
+    Cycles,
+    DriverCycleCount,
+    LastCapacity,
+    UpdatedAt,
+}
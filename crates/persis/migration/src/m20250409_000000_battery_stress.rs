@@ -0,0 +1,59 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(BatteryStress::Table)
+                    .if_not_exists()
+                    .col(pk_auto(BatteryStress::Id))
+                    .col(integer(BatteryStress::SocBand))
+                    .col(integer(BatteryStress::TempBand))
+                    .col(big_integer(BatteryStress::Secs))
+                    .col(big_integer(BatteryStress::UpdatedAt))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .table(BatteryStress::Table)
+                    .name("idx_BatteryStress_band")
+                    .col(BatteryStress::SocBand)
+                    .col(BatteryStress::TempBand)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .table(BatteryStress::Table)
+                    .name("idx_BatteryStress_band")
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(BatteryStress::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+#[derive(DeriveIden)]
+enum BatteryStress {
+    Table,
+    Id,
+    SocBand,
+    TempBand,
+    Secs,
+    UpdatedAt,
+}
@@ -0,0 +1,67 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(BatteryHealth::Table)
+                    .if_not_exists()
+                    .col(pk_auto(BatteryHealth::Id))
+                    .col(string(BatteryHealth::BatteryKey))
+                    .col(big_integer(BatteryHealth::Day))
+                    .col(double(BatteryHealth::Score))
+                    .col(float(BatteryHealth::StateOfHealth))
+                    .col(double_null(BatteryHealth::FadePerYear))
+                    .col(double_null(BatteryHealth::Cycles))
+                    .col(double_null(BatteryHealth::StressScore))
+                    .col(integer(BatteryHealth::Anomalies))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .table(BatteryHealth::Table)
+                    .name("idx_BatteryHealth_key_day")
+                    .col(BatteryHealth::BatteryKey)
+                    .col(BatteryHealth::Day)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .table(BatteryHealth::Table)
+                    .name("idx_BatteryHealth_key_day")
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(BatteryHealth::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+#[derive(DeriveIden)]
+enum BatteryHealth {
+    Table,
+    Id,
+    BatteryKey,
+    Day,
+    Score,
+    StateOfHealth,
+    FadePerYear,
+    Cycles,
+    StressScore,
+    Anomalies,
+}
@@ -0,0 +1,51 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+//完整PowerLimit的JSON，包括扩展参数，旧记录为NULL
+const COLUMNS: [PowerLimitAudit; 3] = [
+    PowerLimitAudit::BeforeLimit,
+    PowerLimitAudit::RequestLimit,
+    PowerLimitAudit::AfterLimit,
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        //SQLite每条ALTER TABLE只能添加一列
+        for column in COLUMNS {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(PowerLimitAudit::Table)
+                        .add_column(ColumnDef::new(column).json().null())
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in COLUMNS {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(PowerLimitAudit::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum PowerLimitAudit {
+    Table,
+    BeforeLimit,
+    RequestLimit,
+    AfterLimit,
+}
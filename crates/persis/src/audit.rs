@@ -0,0 +1,84 @@
+use crate::power_limit_audit;
+use power::PowerLimit;
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+
+//功率修改的来源
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerAuditSource {
+    User,
+    PowerLock,
+    Rule,
+    Restore,
+    Governor,
+    Experiment,
+}
+impl std::fmt::Display for PowerAuditSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            PowerAuditSource::User => "user",
+            PowerAuditSource::PowerLock => "power_lock",
+            PowerAuditSource::Rule => "rule",
+            PowerAuditSource::Restore => "restore",
+            PowerAuditSource::Governor => "governor",
+            PowerAuditSource::Experiment => "experiment",
+        };
+        write!(f, "{}", text)
+    }
+}
+//一次功率修改的记录，after为设置后回读的值
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PowerAudit {
+    //毫秒
+    pub timestamp: i64,
+    pub source: PowerAuditSource,
+    //规则名称或预设名称
+    pub detail: Option<String>,
+    //完整的功率设置，包括扩展参数
+    pub before: PowerLimit,
+    pub request: PowerLimit,
+    pub after: Option<PowerLimit>,
+    pub success: bool,
+    pub error: Option<String>,
+}
+pub async fn insert_power_audit(db: &DatabaseConnection, audit: &PowerAudit) -> Result<i32, DbErr> {
+    let model = power_limit_audit::ActiveModel {
+        timestamp: Set(audit.timestamp),
+        source: Set(audit.source.to_string()),
+        detail: Set(audit.detail.clone()),
+        before_stapm: Set(audit.before.stapm_limit),
+        before_slow: Set(audit.before.slow_limit),
+        before_fast: Set(audit.before.fast_limit),
+        request_stapm: Set(audit.request.stapm_limit),
+        request_slow: Set(audit.request.slow_limit),
+        request_fast: Set(audit.request.fast_limit),
+        after_stapm: Set(audit.after.map(|x| x.stapm_limit)),
+        after_slow: Set(audit.after.map(|x| x.slow_limit)),
+        after_fast: Set(audit.after.map(|x| x.fast_limit)),
+        success: Set(audit.success),
+        error: Set(audit.error.clone()),
+        before_limit: Set(serde_json::to_value(audit.before).ok()),
+        request_limit: Set(serde_json::to_value(audit.request).ok()),
+        after_limit: Set(audit.after.and_then(|x| serde_json::to_value(x).ok())),
+        ..Default::default()
+    };
+    let res = power_limit_audit::Entity::insert(model).exec(db).await?;
+    Ok(res.last_insert_id)
+}
+//按id倒序分页
+pub async fn select_power_audit_page(
+    db: &DatabaseConnection,
+    cursor: Option<i32>,
+    size: u64,
+) -> Result<Vec<power_limit_audit::Model>, DbErr> {
+    let mut query = power_limit_audit::Entity::find();
+    if let Some(cursor) = cursor {
+        query = query.filter(power_limit_audit::Column::Id.lt(cursor));
+    }
+    query
+        .order_by_desc(power_limit_audit::Column::Id)
+        .limit(size)
+        .all(db)
+        .await
+}
@@ -0,0 +1,179 @@
+use crate::battery_cycles;
+use battery::{ExternalBatteryState, State};
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+
+//按累计放电量折算的等效完整循环次数，驱动不提供cycle_count时使用
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CycleCounter {
+    pub battery_key: String,
+    //累计放电量(Wh)
+    pub discharged_wh: f64,
+    //等效完整循环次数，每次放电量按当时的满充容量折算
+    pub cycles: f64,
+    //驱动报告的循环次数
+    pub driver_cycle_count: Option<i32>,
+    pub updated_at: i64,
+    //上次读数的容量(Wh)，跨会话保存，关闭期间的放电也会计入
+    last_capacity: Option<f32>,
+    #[serde(skip)]
+    dirty: bool,
+}
+impl CycleCounter {
+    pub fn new(battery_key: String) -> Self {
+        Self {
+            battery_key,
+            discharged_wh: 0.0,
+            cycles: 0.0,
+            driver_cycle_count: None,
+            updated_at: 0,
+            last_capacity: None,
+            dirty: false,
+        }
+    }
+    //记录一次读数，只有驱动报告放电时容量的下降才计入放电量
+    //state应为驱动的原始状态，满电及未知状态下的容量抖动不计入
+    pub fn observe(
+        &mut self,
+        state: State,
+        capacity: f32,
+        full_capacity: f32,
+        driver_cycle_count: Option<i32>,
+        now: i64,
+    ) {
+        let discharging = matches!(
+            state.0,
+            ExternalBatteryState::Discharging | ExternalBatteryState::Empty
+        );
+        if let Some(last) = self.last_capacity {
+            let drop = (last - capacity) as f64;
+            if discharging && drop > 0.0 && full_capacity > 0.0 {
+                self.discharged_wh += drop;
+                self.cycles += drop / full_capacity as f64;
+            }
+        }
+        self.last_capacity = Some(capacity);
+        if driver_cycle_count.is_some() {
+            self.driver_cycle_count = driver_cycle_count;
+        }
+        self.updated_at = now;
+        self.dirty = true;
+    }
+    //优先使用驱动报告的次数
+    pub fn effective(&self) -> f64 {
+        match self.driver_cycle_count {
+            Some(v) => v as f64,
+            None => self.cycles,
+        }
+    }
+    //每100次循环损失的健康度(0-1)，循环次数不足1次时无意义
+    pub fn wear_per_100_cycles(&self, state_of_health: f32) -> Option<f64> {
+        let cycles = self.effective();
+        match cycles >= 1.0 {
+            true => Some((1.0 - state_of_health as f64).max(0.0) / cycles * 100.0),
+            false => None,
+        }
+    }
+    pub fn health(&self, state_of_health: f32) -> CycleHealth {
+        CycleHealth {
+            battery_key: self.battery_key.clone(),
+            driver_cycle_count: self.driver_cycle_count,
+            software_cycles: self.cycles,
+            discharged_wh: self.discharged_wh,
+            effective_cycles: self.effective(),
+            state_of_health,
+            wear_per_100_cycles: self.wear_per_100_cycles(state_of_health),
+        }
+    }
+}
+//驱动值与软件计数并列，按有效循环次数计算损耗
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CycleHealth {
+    pub battery_key: String,
+    pub driver_cycle_count: Option<i32>,
+    pub software_cycles: f64,
+    pub discharged_wh: f64,
+    pub effective_cycles: f64,
+    pub state_of_health: f32,
+    pub wear_per_100_cycles: Option<f64>,
+}
+impl From<battery_cycles::Model> for CycleCounter {
+    fn from(model: battery_cycles::Model) -> Self {
+        Self {
+            battery_key: model.battery_key,
+            discharged_wh: model.discharged_wh,
+            cycles: model.cycles,
+            driver_cycle_count: model.driver_cycle_count,
+            updated_at: model.updated_at,
+            last_capacity: model.last_capacity,
+            dirty: false,
+        }
+    }
+}
+//读取电池的累计循环，不存在时从0开始
+pub async fn load_cycle_counter(
+    db: &DatabaseConnection,
+    battery_key: &str,
+) -> Result<CycleCounter, DbErr> {
+    let row = battery_cycles::Entity::find()
+        .filter(battery_cycles::Column::BatteryKey.eq(battery_key))
+        .one(db)
+        .await?;
+    Ok(match row {
+        Some(row) => row.into(),
+        None => CycleCounter::new(battery_key.to_string()),
+    })
+}
+//有新读数时按battery_key写入
+pub async fn save_cycle_counter(
+    db: &DatabaseConnection,
+    counter: &mut CycleCounter,
+) -> Result<(), DbErr> {
+    if !counter.dirty {
+        return Ok(());
+    }
+    battery_cycles::Entity::insert(battery_cycles::ActiveModel {
+        id: NotSet,
+        battery_key: Set(counter.battery_key.clone()),
+        discharged_wh: Set(counter.discharged_wh),
+        cycles: Set(counter.cycles),
+        driver_cycle_count: Set(counter.driver_cycle_count),
+        last_capacity: Set(counter.last_capacity),
+        updated_at: Set(counter.updated_at),
+    })
+    .on_conflict(
+        sea_query::OnConflict::column(battery_cycles::Column::BatteryKey)
+            .update_columns([
+                battery_cycles::Column::DischargedWh,
+                battery_cycles::Column::Cycles,
+                battery_cycles::Column::DriverCycleCount,
+                battery_cycles::Column::LastCapacity,
+                battery_cycles::Column::UpdatedAt,
+            ])
+            .to_owned(),
+    )
+    .exec(db)
+    .await?;
+    counter.dirty = false;
+    Ok(())
+}
+//其他电池最后一次读数的时间，之后的数据只属于当前电池，没有用过其他电池时为None
+pub async fn battery_switched_at(
+    db: &DatabaseConnection,
+    battery_key: &str,
+) -> Result<Option<i64>, DbErr> {
+    let row = battery_cycles::Entity::find()
+        .filter(battery_cycles::Column::BatteryKey.ne(battery_key))
+        .order_by_desc(battery_cycles::Column::UpdatedAt)
+        .one(db)
+        .await?;
+    Ok(row.map(|x| x.updated_at))
+}
+pub async fn select_battery_cycles(
+    db: &DatabaseConnection,
+) -> Result<Vec<battery_cycles::Model>, DbErr> {
+    battery_cycles::Entity::find()
+        .order_by_desc(battery_cycles::Column::UpdatedAt)
+        .all(db)
+        .await
+}
@@ -1,129 +1,197 @@
-use std::str::FromStr;
-
-use crate::battery_realtime;
-use sea_orm::*;
 use chrono::prelude::*;
+use sea_orm::*;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Aggregation {
+    Avg,
+    Min,
+    Max,
+    //分组内按order_field排序的第一条/最后一条
+    First,
+    Last,
+    Sum,
+    Count,
+}
+//输出列，alias为结果中的列名，需要与返回类型的字段对应
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ColumnSpec {
+    pub column: String,
+    pub aggregation: Aggregation,
+    pub alias: String,
+}
+impl ColumnSpec {
+    pub fn new(column: &str, aggregation: Aggregation) -> Self {
+        Self {
+            column: column.to_string(),
+            aggregation,
+            alias: column.to_string(),
+        }
+    }
+    pub fn alias(mut self, alias: &str) -> Self {
+        self.alias = alias.to_string();
+        self
+    }
+}
+//时间分组后的列固定命名为timestamp，值为分组的起始时间
 pub struct DownSampleParams {
-    pub time_formater: String,
     pub table_name: String,
     pub time_field: String,
     pub order_field: String,
+    pub columns: Vec<ColumnSpec>,
     pub end_time: i64,
     pub start_time: i64,
-    pub interval_secs: i64,
+    //与time_field的单位相同，均为毫秒
+    pub interval: i64,
 }
 impl Default for DownSampleParams {
     fn default() -> Self {
-        let end_time = Utc::now().timestamp();
+        let end_time = Utc::now().timestamp_millis();
         Self {
-            time_formater: "%Y-%m-%dT%H:%M:%SZ".to_string(), //ISO 8601格式
             table_name: "memory_battery_status".to_string(),
             time_field: "timestamp".to_string(),
             order_field: "id".to_string(),
+            columns: battery_columns(),
             end_time,
-            start_time: end_time - 10,
-            interval_secs: 1,
+            start_time: end_time - 10_000,
+            interval: 1000,
         }
     }
 }
-pub fn build_down_sample_sql(params: &DownSampleParams) -> String {
-    let formater = &params.time_formater;
-    let table_name = &params.table_name;
-    let time_field = &params.time_field;
-    let order_field = &params.order_field;
-    let end_time = params.end_time;
-    let start_time = params.start_time;
-    let interval_secs = params.interval_secs;
+//battery_columns降采样的结果
+#[derive(Clone, Debug, PartialEq, FromQueryResult)]
+pub struct BatterySample {
+    pub timestamp: i64,
+    pub state: String,
+    pub percentage: f32,
+    pub energy_rate: f32,
+    pub voltage: f32,
+    pub cpu_load: f32,
+    pub temperature: Option<f32>,
+    pub cycle_count: Option<i32>,
+    pub time_to_empty_secs: Option<i64>,
+    pub time_to_full_secs: Option<i64>,
+}
+//电池数据：状态取最后一条，读数取平均
+pub fn battery_columns() -> Vec<ColumnSpec> {
+    let mut columns = vec![ColumnSpec::new("state", Aggregation::Last)];
+    for column in ["percentage", "energy_rate", "voltage", "cpu_load"] {
+        columns.push(ColumnSpec::new(column, Aggregation::Avg));
+    }
+    columns.push(ColumnSpec::new("temperature", Aggregation::Avg));
+    //循环次数和剩余时间是瞬时估计，取最后一条
+    for column in ["cycle_count", "time_to_empty_secs", "time_to_full_secs"] {
+        columns.push(ColumnSpec::new(column, Aggregation::Last));
+    }
+    columns
+}
+//功率数据：limit同样取平均以反映分组内的调整
+pub fn power_columns() -> Vec<ColumnSpec> {
+    [
+        "stapm_limit",
+        "stapm_value",
+        "slow_limit",
+        "slow_value",
+        "fast_limit",
+        "fast_value",
+    ]
+    .iter()
+    .map(|x| ColumnSpec::new(x, Aggregation::Avg))
+    .collect()
+}
+//表名和列名无法绑定参数，只允许字母数字下划线并加引号
+fn quote(ident: &str) -> Result<String, DbErr> {
+    match !ident.is_empty() && ident.chars().all(|x| x.is_ascii_alphanumeric() || x == '_') {
+        true => Ok(format!("\"{}\"", ident)),
+        false => Err(DbErr::Custom(format!("invalid identifier({})", ident))),
+    }
+}
+//时间范围为(start_time, end_time]，按interval对齐分组
+pub fn build_down_sample_sql(params: &DownSampleParams) -> Result<Statement, DbErr> {
+    if params.interval <= 0 {
+        return Err(DbErr::Custom(format!(
+            "invalid interval({})",
+            params.interval
+        )));
+    }
+    let table_name = quote(&params.table_name)?;
+    let time_field = quote(&params.time_field)?;
+    let order_field = quote(&params.order_field)?;
+    let mut columns = Vec::with_capacity(params.columns.len());
+    for spec in &params.columns {
+        let column = quote(&spec.column)?;
+        let expr = match spec.aggregation {
+            Aggregation::Avg => format!("AVG({column})"),
+            Aggregation::Min => format!("MIN({column})"),
+            Aggregation::Max => format!("MAX({column})"),
+            //每组只有一行rn为1，MAX取出该行的值
+            Aggregation::First => format!("MAX(CASE WHEN rn_first = 1 THEN {column} END)"),
+            Aggregation::Last => format!("MAX(CASE WHEN rn_last = 1 THEN {column} END)"),
+            Aggregation::Sum => format!("SUM({column})"),
+            Aggregation::Count => format!("COUNT({column})"),
+        };
+        columns.push(format!("{expr} AS {}", quote(&spec.alias)?));
+    }
     let sql = format!(
-        r"
-WITH GroupData AS (
-SELECT
-strftime('{formater}', {time_field}/{interval_secs}*{interval_secs} , 'unixepoch') AS time_group,
-AVG(percentage) AS percentage,
-AVG(energy_rate) AS energy_rate,
-AVG(voltage) AS voltage,
-AVG(cpu_load) AS cpu_load,
-MAX({time_field}) AS last_timestamp
-FROM
-{table_name}
-WHERE {time_field}>{start_time} and {time_field}<={end_time}
-GROUP BY
-time_group
-),
-UniqueBatteryStatus AS (
+        r#"
+WITH Source AS (
 SELECT
-    {time_field},
-    state,
-    ROW_NUMBER() OVER (PARTITION BY {time_field} ORDER BY {order_field} DESC) AS rn --排序字段用于在分组内的数据排序
-FROM
-    {table_name}
-WHERE {time_field}>{start_time} and {time_field}<={end_time}
+    *,
+    {time_field} / $3 * $3 AS time_group,
+    ROW_NUMBER() OVER (PARTITION BY {time_field} / $3 ORDER BY {order_field} ASC) AS rn_first,
+    ROW_NUMBER() OVER (PARTITION BY {time_field} / $3 ORDER BY {order_field} DESC) AS rn_last
+FROM {table_name}
+WHERE {time_field} > $1 AND {time_field} <= $2
 )
 SELECT
-g.time_group,
-g.percentage,
-g.energy_rate,
-g.voltage,
-g.cpu_load,
-b.state
-FROM
-GroupData g
-JOIN UniqueBatteryStatus b ON
-g.last_timestamp = b.{time_field}
-AND b.rn = 1
-ORDER BY
-g.time_group;
-    "
+    time_group AS "timestamp",
+    {columns}
+FROM Source
+GROUP BY time_group
+ORDER BY time_group
+"#,
+        columns = columns.join(",\n    "),
     );
-    //println!("{}",&sql);
-    sql
-}
-pub async fn down_sample(
-    db:&DatabaseConnection,
-    params: &DownSampleParams,
-) -> Result<Vec<battery_realtime::Model>, DbErr> {
-    let res = db.query_all(Statement::from_string(
+    Ok(Statement::from_sql_and_values(
         sea_orm::DatabaseBackend::Sqlite,
-        build_down_sample_sql(params),
+        sql,
+        [
+            params.start_time.into(),
+            params.end_time.into(),
+            params.interval.into(),
+        ],
     ))
-    .await?;
-    let res: Vec<battery_realtime::Model> = res
+}
+//降采样结果写入target_table，已存在的时间分组覆盖更新，target_table的timestamp需要唯一
+pub fn build_upsert_sql(target_table: &str, params: &DownSampleParams) -> Result<Statement, DbErr> {
+    let select = build_down_sample_sql(params)?;
+    let target_table = quote(target_table)?;
+    let names: Vec<String> = params
+        .columns
+        .iter()
+        .map(|x| quote(&x.alias))
+        .collect::<Result<_, _>>()?;
+    let updates: Vec<String> = names
         .iter()
-        .map(|row| {
-            row.try_get_many::<(String, String, f32, f32, f32, f32)>(
-                "",
-                &[
-                    "time_group".to_string(),
-                    "state".to_string(),
-                    "percentage".to_string(),
-                    "energy_rate".to_string(),
-                    "voltage".to_string(),
-                    "cpu_load".to_string(),
-                ],
-            )
-            .unwrap()
-        })
-        .map(
-            |(
-                time_group,
-                state,
-                percentage,
-                energy_rate,
-                voltage,
-                cpu_load,
-            )| {
-                battery_realtime::Model {
-                    timestamp: DateTime::<Utc>::from_str(time_group.as_str())
-                        .unwrap()
-                        .timestamp(),
-                    state,
-                    percentage,
-                    energy_rate,
-                    voltage,
-                    cpu_load,
-                }
-            },
-        )
+        .map(|x| format!("{x} = excluded.{x}"))
         .collect();
-    Ok(res)
-}
\ No newline at end of file
+    //INSERT ... SELECT带ON CONFLICT时需要WHERE避免语法歧义，无数据时不插入
+    let sql = format!(
+        r#"
+INSERT INTO {target_table} ("timestamp", {names})
+SELECT "timestamp", {names} FROM ({select}) WHERE true
+ON CONFLICT("timestamp") DO UPDATE SET {updates}
+"#,
+        names = names.join(", "),
+        select = select.sql,
+        updates = updates.join(", "),
+    );
+    Ok(Statement { sql, ..select })
+}
+pub async fn down_sample<T: FromQueryResult>(
+    db: &DatabaseConnection,
+    params: &DownSampleParams,
+) -> Result<Vec<T>, DbErr> {
+    T::find_by_statement(build_down_sample_sql(params)?)
+        .all(db)
+        .await
+}
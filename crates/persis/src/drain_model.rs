@@ -0,0 +1,171 @@
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+
+//至少需要的样本数，少于此数量不拟合
+const MIN_SAMPLES: usize = 30;
+//样本数达到此数量时置信度不再因样本数打折
+const FULL_CONFIDENCE_SAMPLES: f64 = 600.0;
+
+//放电时每分钟的cpu占用、stapm及放电功率
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, FromQueryResult)]
+pub struct DrainSample {
+    pub cpu_load: f32,
+    pub stapm_limit: f32,
+    //放电功率(W)，为正数
+    pub drain: f32,
+}
+//drain = c0 + c1*load + c2*stapm + c3*load*stapm，load为0-1
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct DrainModel {
+    pub coefficients: [f64; 4],
+    //拟合优度(0-1)
+    pub r2: f64,
+    pub samples: usize,
+}
+fn features(cpu_load: f32, stapm_limit: f32) -> [f64; 4] {
+    let load = cpu_load as f64;
+    let stapm = stapm_limit as f64;
+    [1.0, load, stapm, load * stapm]
+}
+//高斯消元求解正规方程，矩阵奇异时返回None
+fn solve(mut a: [[f64; 4]; 4], mut b: [f64; 4]) -> Option<[f64; 4]> {
+    for col in 0..4 {
+        let pivot = (col..4).max_by(|x, y| a[*x][col].abs().total_cmp(&a[*y][col].abs()))?;
+        if a[pivot][col].abs() < 1e-9 {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        let pivot_row = a[col];
+        for row in 0..4 {
+            if row != col {
+                let factor = a[row][col] / pivot_row[col];
+                for (k, v) in a[row].iter_mut().enumerate().skip(col) {
+                    *v -= factor * pivot_row[k];
+                }
+                b[row] -= factor * b[col];
+            }
+        }
+    }
+    Some([
+        b[0] / a[0][0],
+        b[1] / a[1][1],
+        b[2] / a[2][2],
+        b[3] / a[3][3],
+    ])
+}
+impl DrainModel {
+    //最小二乘拟合，样本不足或stapm从未变化时返回None
+    pub fn fit(samples: &[DrainSample]) -> Option<Self> {
+        if samples.len() < MIN_SAMPLES {
+            return None;
+        }
+        let mut xtx = [[0.0; 4]; 4];
+        let mut xty = [0.0; 4];
+        for sample in samples {
+            let x = features(sample.cpu_load, sample.stapm_limit);
+            for i in 0..4 {
+                for j in 0..4 {
+                    xtx[i][j] += x[i] * x[j];
+                }
+                xty[i] += x[i] * sample.drain as f64;
+            }
+        }
+        let coefficients = solve(xtx, xty)?;
+        let mut model = Self {
+            coefficients,
+            r2: 0.0,
+            samples: samples.len(),
+        };
+        let mean = samples.iter().map(|x| x.drain as f64).sum::<f64>() / samples.len() as f64;
+        let (mut ss_res, mut ss_tot) = (0.0, 0.0);
+        for sample in samples {
+            let predicted = model.predict(sample.cpu_load, sample.stapm_limit) as f64;
+            ss_res += (sample.drain as f64 - predicted).powi(2);
+            ss_tot += (sample.drain as f64 - mean).powi(2);
+        }
+        model.r2 = match ss_tot > 0.0 {
+            true => (1.0 - ss_res / ss_tot).clamp(0.0, 1.0),
+            false => 0.0,
+        };
+        Some(model)
+    }
+    pub fn predict(&self, cpu_load: f32, stapm_limit: f32) -> f32 {
+        let x = features(cpu_load, stapm_limit);
+        let drain: f64 = x.iter().zip(self.coefficients).map(|(x, c)| x * c).sum();
+        drain as f32
+    }
+    //综合拟合优度与样本数量的置信度(0-1)
+    pub fn confidence(&self) -> f32 {
+        (self.r2 * (self.samples as f64 / FULL_CONFIDENCE_SAMPLES).min(1.0)) as f32
+    }
+    //预测的续航时长(h)，预测放电功率不为正时返回None
+    pub fn runtime_hours(&self, capacity_wh: f32, cpu_load: f32, stapm_limit: f32) -> Option<f32> {
+        let drain = self.predict(cpu_load, stapm_limit);
+        match drain > 0.0 {
+            true => Some(capacity_wh / drain),
+            false => None,
+        }
+    }
+}
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PowerRecommendation {
+    pub limit: power::PowerLimit,
+    pub target_hours: f32,
+    //按推荐功率预测的续航(h)
+    pub predicted_hours: Option<f32>,
+    pub confidence: f32,
+    //放电期间的平均cpu占用(0-1)
+    pub typical_load: f32,
+    pub model: DrainModel,
+}
+//在安全范围内从高到低选择能满足目标续航的最大stapm，均不满足时使用最小值
+pub fn recommend(
+    model: &DrainModel,
+    cpu_family: i32,
+    capacity_wh: f32,
+    target_hours: f32,
+    typical_load: f32,
+) -> Option<PowerRecommendation> {
+    let range = power::LimitRange::from_family(cpu_family)?;
+    let mut stapm = range.stapm_limit.max.floor();
+    while stapm > range.stapm_limit.min {
+        match model.runtime_hours(capacity_wh, typical_load, stapm) {
+            Some(hours) if hours >= target_hours => break,
+            _ => stapm -= 1.0,
+        }
+    }
+    let limit = power::limit_for_stapm(cpu_family, stapm.max(range.stapm_limit.min))?;
+    Some(PowerRecommendation {
+        limit,
+        target_hours,
+        predicted_hours: model.runtime_hours(capacity_wh, typical_load, limit.stapm_limit),
+        confidence: model.confidence(),
+        typical_load,
+        model: *model,
+    })
+}
+//放电期间battery与power的分钟数据按时间(毫秒)对齐
+pub async fn select_drain_samples(
+    db: &DatabaseConnection,
+    start: i64,
+    end: i64,
+) -> Result<Vec<DrainSample>, DbErr> {
+    DrainSample::find_by_statement(Statement::from_sql_and_values(
+        sea_orm::DatabaseBackend::Sqlite,
+        r#"
+SELECT
+    b."cpu_load" AS cpu_load,
+    p."stapm_limit" AS stapm_limit,
+    -b."energy_rate" AS drain
+FROM "battery_one_minutes" b
+JOIN "power_one_minutes" p ON b."timestamp" = p."timestamp"
+WHERE p."timestamp" BETWEEN $1 AND $2
+    AND b."state" = 'discharging'
+    AND b."energy_rate" < 0
+"#,
+        [start.into(), end.into()],
+    ))
+    .all(db)
+    .await
+}
@@ -6,7 +6,8 @@ use serde::{Deserialize, Serialize};
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
 #[sea_orm(table_name = "battery_realtime")]
 pub struct Model {
-    #[sea_orm(primary_key, auto_increment = false)]
+    #[sea_orm(primary_key)]
+    pub id: i32,
     pub timestamp: i64,
     pub state: String,
     #[sea_orm(column_type = "Float")]
@@ -17,6 +18,11 @@ pub struct Model {
     pub voltage: f32,
     #[sea_orm(column_type = "Float")]
     pub cpu_load: f32,
+    #[sea_orm(column_type = "Float", nullable)]
+    pub temperature: Option<f32>,
+    pub cycle_count: Option<i32>,
+    pub time_to_empty_secs: Option<i64>,
+    pub time_to_full_secs: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
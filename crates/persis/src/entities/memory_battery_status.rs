@@ -18,6 +18,11 @@ pub struct Model {
     pub voltage: f32,
     #[sea_orm(column_type = "Float")]
     pub cpu_load: f32,
+    #[sea_orm(column_type = "Float", nullable)]
+    pub temperature: Option<f32>,
+    pub cycle_count: Option<i32>,
+    pub time_to_empty_secs: Option<i64>,
+    pub time_to_full_secs: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
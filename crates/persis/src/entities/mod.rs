@@ -2,7 +2,19 @@
 
 pub mod prelude;
 
+pub mod battery_cycles;
+pub mod battery_health;
+pub mod battery_one_day;
+pub mod battery_one_hour;
 pub mod battery_one_minutes;
 pub mod battery_realtime;
 pub mod battery_state_history;
+pub mod battery_stress;
 pub mod memory_battery_status;
+pub mod memory_power_status;
+pub mod power_experiment;
+pub mod power_experiment_result;
+pub mod power_limit_audit;
+pub mod power_one_minutes;
+pub mod power_realtime;
+pub mod sleep_sessions;
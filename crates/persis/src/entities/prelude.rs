@@ -1,6 +1,18 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.4
 
+pub use super::battery_cycles::Entity as BatteryCycles;
+pub use super::battery_health::Entity as BatteryHealth;
+pub use super::battery_one_day::Entity as BatteryOneDay;
+pub use super::battery_one_hour::Entity as BatteryOneHour;
 pub use super::battery_one_minutes::Entity as BatteryOneMinutes;
 pub use super::battery_realtime::Entity as BatteryRealtime;
 pub use super::battery_state_history::Entity as BatteryStateHistory;
+pub use super::battery_stress::Entity as BatteryStress;
 pub use super::memory_battery_status::Entity as MemoryBatteryStatus;
+pub use super::memory_power_status::Entity as MemoryPowerStatus;
+pub use super::power_experiment::Entity as PowerExperiment;
+pub use super::power_experiment_result::Entity as PowerExperimentResult;
+pub use super::power_limit_audit::Entity as PowerLimitAudit;
+pub use super::power_one_minutes::Entity as PowerOneMinutes;
+pub use super::power_realtime::Entity as PowerRealtime;
+pub use super::sleep_sessions::Entity as SleepSessions;
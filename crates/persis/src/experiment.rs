@@ -0,0 +1,111 @@
+use crate::{power_experiment, power_experiment_result};
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+
+//t分布双侧95%临界值，下标为自由度-1，超过30时使用正态近似
+const T_95: [f32; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+    2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+    2.052, 2.048, 2.045, 2.042,
+];
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct MetricStats {
+    pub samples: usize,
+    pub mean: f32,
+    pub std_dev: f32,
+    //均值的95%置信区间
+    pub ci_low: f32,
+    pub ci_high: f32,
+}
+impl MetricStats {
+    //样本少于2个时无法估计方差，返回None
+    pub fn from_values(values: &[f32]) -> Option<Self> {
+        let n = values.len();
+        if n < 2 {
+            return None;
+        }
+        let mean = values.iter().sum::<f32>() / n as f32;
+        let variance = values.iter().map(|x| (x - mean).powi(2)).sum::<f32>() / (n - 1) as f32;
+        let std_dev = variance.sqrt();
+        let t = T_95.get(n - 2).copied().unwrap_or(1.96);
+        let half = t * std_dev / (n as f32).sqrt();
+        Some(Self {
+            samples: n,
+            mean,
+            std_dev,
+            ci_low: mean - half,
+            ci_high: mean + half,
+        })
+    }
+}
+//某个预设在某项指标上的统计，每个阶段的均值作为一个样本，避免秒级数据自相关导致区间过窄
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ExperimentSummary {
+    pub preset: String,
+    //drain(W)、temperature(℃)、cpu_load(0-1)
+    pub metric: String,
+    pub stats: MetricStats,
+}
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ExperimentReport {
+    //毫秒
+    pub started_at: i64,
+    pub ended_at: i64,
+    pub interval_secs: u32,
+    pub rounds: u32,
+    pub summaries: Vec<ExperimentSummary>,
+}
+pub async fn insert_experiment(
+    db: &DatabaseConnection,
+    report: &ExperimentReport,
+) -> Result<i32, DbErr> {
+    let txn = db.begin().await?;
+    let res = power_experiment::Entity::insert(power_experiment::ActiveModel {
+        started_at: Set(report.started_at),
+        ended_at: Set(report.ended_at),
+        interval_secs: Set(report.interval_secs as i32),
+        rounds: Set(report.rounds as i32),
+        ..Default::default()
+    })
+    .exec(&txn)
+    .await?;
+    let experiment_id = res.last_insert_id;
+    let rows: Vec<power_experiment_result::ActiveModel> = report
+        .summaries
+        .iter()
+        .map(|x| power_experiment_result::ActiveModel {
+            experiment_id: Set(experiment_id),
+            preset: Set(x.preset.clone()),
+            metric: Set(x.metric.clone()),
+            samples: Set(x.stats.samples as i32),
+            mean: Set(x.stats.mean),
+            std_dev: Set(x.stats.std_dev),
+            ci_low: Set(x.stats.ci_low),
+            ci_high: Set(x.stats.ci_high),
+            ..Default::default()
+        })
+        .collect();
+    if !rows.is_empty() {
+        power_experiment_result::Entity::insert_many(rows)
+            .exec(&txn)
+            .await?;
+    }
+    txn.commit().await?;
+    Ok(experiment_id)
+}
+//最近的实验及其结果，按id倒序
+pub async fn select_experiments(
+    db: &DatabaseConnection,
+    size: u64,
+) -> Result<Vec<(power_experiment::Model, Vec<power_experiment_result::Model>)>, DbErr> {
+    let experiments = power_experiment::Entity::find()
+        .order_by_desc(power_experiment::Column::Id)
+        .limit(size)
+        .all(db)
+        .await?;
+    let results = experiments
+        .load_many(power_experiment_result::Entity, db)
+        .await?;
+    Ok(experiments.into_iter().zip(results).collect())
+}
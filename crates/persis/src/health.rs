@@ -0,0 +1,231 @@
+use crate::{
+    battery_health, battery_state_history, cycles::battery_switched_at, sleep_sessions,
+    stress::select_stress,
+};
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+
+//综合健康度(0-100)：各项先换算为0-100分，再按权重加权平均，无数据的项不参与，剩余权重重新归一化
+// - 健康度(SoH) 40%：100%为100分，60%及以下为0分
+// - 容量衰减速度 20%：满充容量每年衰减10%及以上为0分，记录跨度不足7天时不计
+// - 循环次数 15%：按1000次额定寿命线性扣分，优先使用驱动报告的次数
+// - 日历老化压力 15%：100减去电量/温度直方图的压力分
+// - 异常 10%：近30天内每个异常扣20分，异常包括高温段、满充容量骤降和睡眠耗电过快
+//衰减速度和异常只统计换到当前电池之后的数据，老化压力在换电池时重新统计
+const SOH_WEIGHT: f64 = 0.4;
+const FADE_WEIGHT: f64 = 0.2;
+const CYCLES_WEIGHT: f64 = 0.15;
+const STRESS_WEIGHT: f64 = 0.15;
+const ANOMALY_WEIGHT: f64 = 0.1;
+const RATED_CYCLES: f64 = 1000.0;
+const MIN_FADE_SPAN_MS: i64 = 7 * DAY_MS;
+const YEAR_MS: f64 = 365.0 * DAY_MS as f64;
+const ANOMALY_SPAN_MS: i64 = 30 * DAY_MS;
+//历史段平均温度不低于此值(°C)视为高温
+const ANOMALY_TEMPERATURE: f32 = 50.0;
+//满充容量比上一段低5%以上视为骤降
+const ANOMALY_CAPACITY_DROP: f32 = 0.95;
+//睡眠期间每小时消耗超过5%电量视为耗电过快
+const ANOMALY_SLEEP_RATE: f32 = 0.05;
+pub const DAY_MS: i64 = 86_400_000;
+
+//按UTC对齐的当天开始时间
+pub fn health_day(now: i64) -> i64 {
+    now.div_euclid(DAY_MS) * DAY_MS
+}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct HealthInputs {
+    pub state_of_health: f32,
+    //满充容量的年衰减比例(0-1)
+    pub fade_per_year: Option<f64>,
+    pub cycles: Option<f64>,
+    pub stress_score: Option<f64>,
+    pub anomalies: i32,
+}
+impl HealthInputs {
+    pub fn score(&self) -> f64 {
+        let components = [
+            (
+                Some((self.state_of_health as f64 - 0.6) / 0.4 * 100.0),
+                SOH_WEIGHT,
+            ),
+            (self.fade_per_year.map(|x| 100.0 - x * 1000.0), FADE_WEIGHT),
+            (
+                self.cycles.map(|x| 100.0 - x / RATED_CYCLES * 100.0),
+                CYCLES_WEIGHT,
+            ),
+            (self.stress_score.map(|x| 100.0 - x), STRESS_WEIGHT),
+            (Some(100.0 - self.anomalies as f64 * 20.0), ANOMALY_WEIGHT),
+        ];
+        let (sum, weight) = components
+            .iter()
+            .filter_map(|(score, weight)| score.map(|x| (x.clamp(0.0, 100.0) * weight, weight)))
+            .fold((0.0, 0.0), |(sum, total), (x, weight)| {
+                (sum + x, total + weight)
+            });
+        sum / weight
+    }
+}
+//从prev降到curr时越过的阈值
+pub fn crossed_below<T: PartialOrd + Copy>(prev: T, curr: T, thresholds: &[T]) -> Vec<T> {
+    thresholds
+        .iter()
+        .copied()
+        .filter(|x| prev >= *x && curr < *x)
+        .collect()
+}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HealthUpdate {
+    //上一次计算的结果，首次计算时为None
+    pub previous: Option<battery_health::Model>,
+    pub current: battery_health::Model,
+}
+impl HealthUpdate {
+    //综合健康度越过里程碑或健康度越过阈值时的提醒，首次计算只作为基线
+    pub fn alerts(&self, milestones: &[f64], soh_thresholds: &[f32]) -> Vec<String> {
+        let Some(previous) = &self.previous else {
+            return Vec::new();
+        };
+        let current = &self.current;
+        let mut alerts: Vec<String> = crossed_below(previous.score, current.score, milestones)
+            .into_iter()
+            .map(|x| format!("电池综合健康度降至{:.0}，低于{:.0}", current.score, x))
+            .collect();
+        alerts.extend(
+            crossed_below(
+                previous.state_of_health,
+                current.state_of_health,
+                soh_thresholds,
+            )
+            .into_iter()
+            .map(|x| {
+                format!(
+                    "电池健康度降至{:.1}%，低于{:.0}%",
+                    current.state_of_health * 100.0,
+                    x * 100.0
+                )
+            }),
+        );
+        alerts
+    }
+}
+//满充容量的年衰减比例，取since之后最早和最新的历史段
+async fn fade_per_year(db: &DatabaseConnection, since: i64) -> Result<Option<f64>, DbErr> {
+    let find = || {
+        battery_state_history::Entity::find()
+            .filter(battery_state_history::Column::Timestamp.gte(since))
+            .filter(battery_state_history::Column::FullCapacity.gt(0.0))
+    };
+    let first = find()
+        .order_by_asc(battery_state_history::Column::Timestamp)
+        .one(db)
+        .await?;
+    let last = find()
+        .order_by_desc(battery_state_history::Column::Timestamp)
+        .one(db)
+        .await?;
+    let (Some(first), Some(last)) = (first, last) else {
+        return Ok(None);
+    };
+    let span = last.timestamp - first.timestamp;
+    if span < MIN_FADE_SPAN_MS {
+        return Ok(None);
+    }
+    let fade = (first.full_capacity - last.full_capacity) as f64 / first.full_capacity as f64;
+    Ok(Some(fade / (span as f64 / YEAR_MS)))
+}
+//baseline之前的历史段属于其他电池，不参与满充容量的比较
+async fn count_anomalies(db: &DatabaseConnection, since: i64, baseline: i64) -> Result<i32, DbErr> {
+    let hot = battery_state_history::Entity::find()
+        .filter(battery_state_history::Column::Timestamp.gte(since))
+        .filter(battery_state_history::Column::Temperature.gte(ANOMALY_TEMPERATURE))
+        .count(db)
+        .await?;
+    let drops: i64 = db
+        .query_one(Statement::from_sql_and_values(
+            sea_orm::DatabaseBackend::Sqlite,
+            r#"
+SELECT COUNT(*) AS "count" FROM (
+    SELECT "timestamp", "full_capacity",
+    LAG("full_capacity") OVER (ORDER BY "timestamp") AS "prev_full_capacity"
+    FROM "battery_state_history"
+    WHERE "timestamp" >= $3
+)
+WHERE "timestamp" >= $1 AND "full_capacity" < "prev_full_capacity" * $2
+"#,
+            [since.into(), ANOMALY_CAPACITY_DROP.into(), baseline.into()],
+        ))
+        .await?
+        .map_or(Ok(0), |row| row.try_get("", "count"))?;
+    let sleep = sleep_sessions::Entity::find()
+        .filter(sleep_sessions::Column::StartAt.gte(since))
+        .filter(sleep_sessions::Column::PercentageRate.gt(ANOMALY_SLEEP_RATE))
+        .count(db)
+        .await?;
+    Ok((hot + drops as u64 + sleep) as i32)
+}
+pub async fn health_inputs(
+    db: &DatabaseConnection,
+    battery_key: &str,
+    state_of_health: f32,
+    cycles: Option<f64>,
+    now: i64,
+) -> Result<HealthInputs, DbErr> {
+    let baseline = battery_switched_at(db, battery_key)
+        .await?
+        .unwrap_or(i64::MIN);
+    let stress = select_stress(db).await?;
+    Ok(HealthInputs {
+        state_of_health,
+        fade_per_year: fade_per_year(db, baseline).await?,
+        cycles,
+        stress_score: (stress.total_secs > 0).then_some(stress.stress_score),
+        anomalies: count_anomalies(db, (now - ANOMALY_SPAN_MS).max(baseline), baseline).await?,
+    })
+}
+//每个电池每天计算一次，当天已计算时返回None
+pub async fn update_health(
+    db: &DatabaseConnection,
+    battery_key: &str,
+    state_of_health: f32,
+    cycles: Option<f64>,
+    now: i64,
+) -> Result<Option<HealthUpdate>, DbErr> {
+    let day = health_day(now);
+    let previous = battery_health::Entity::find()
+        .filter(battery_health::Column::BatteryKey.eq(battery_key))
+        .order_by_desc(battery_health::Column::Day)
+        .one(db)
+        .await?;
+    if previous.as_ref().is_some_and(|x| x.day >= day) {
+        return Ok(None);
+    }
+    let inputs = health_inputs(db, battery_key, state_of_health, cycles, now).await?;
+    let current = battery_health::ActiveModel {
+        id: NotSet,
+        battery_key: Set(battery_key.to_string()),
+        day: Set(day),
+        score: Set(inputs.score()),
+        state_of_health: Set(inputs.state_of_health),
+        fade_per_year: Set(inputs.fade_per_year),
+        cycles: Set(inputs.cycles),
+        stress_score: Set(inputs.stress_score),
+        anomalies: Set(inputs.anomalies),
+    }
+    .insert(db)
+    .await?;
+    Ok(Some(HealthUpdate { previous, current }))
+}
+//按天倒序
+pub async fn select_health(
+    db: &DatabaseConnection,
+    battery_key: &str,
+    size: u64,
+) -> Result<Vec<battery_health::Model>, DbErr> {
+    battery_health::Entity::find()
+        .filter(battery_health::Column::BatteryKey.eq(battery_key))
+        .order_by_desc(battery_health::Column::Day)
+        .limit(size)
+        .all(db)
+        .await
+}
@@ -2,7 +2,33 @@ mod entities;
 pub use entities::*;
 mod store;
 pub use store::*;
-mod down_sample;
+pub mod down_sample;
+mod power_store;
+mod audit;
+pub use audit::*;
+mod cycles;
+pub use cycles::*;
+mod drain_model;
+pub use drain_model::*;
+mod experiment;
+pub use experiment::*;
+mod health;
+pub use health::*;
+mod report;
+pub use report::*;
+mod retention;
+pub use retention::*;
+mod rollup;
+mod sample_buffer;
+pub use sample_buffer::*;
+mod segments;
+pub use segments::*;
+mod sleep;
+pub use sleep::*;
+mod stress;
+pub use stress::*;
+mod series;
+pub use series::*;
 mod manager;
 pub use manager::*;
 #[cfg(test)]
@@ -38,12 +64,12 @@ mod tests {
     }
     #[tokio::test]
     async fn select_history_page() {
-        let now = Utc::now().timestamp();
+        let now = Utc::now().timestamp_millis();
         let manager = manager::Manager::build(&"migration/database/debug.db".to_string(), 10)
             .await
             .unwrap();
         let rows = manager
-            .select_history_page(None, 30, now - Duration::days(1).num_seconds(), now)
+            .select_history_page(None, 30, now - Duration::days(1).num_milliseconds(), now)
             .await
             .unwrap();
         dbg!(rows);
@@ -72,21 +98,961 @@ mod tests {
         }
 
         let now_millis = Utc::now().timestamp_millis();
-        let end = Utc::now().timestamp();
-        let r1 = down_sample::down_sample(
-            &store.mem_db,
-            &down_sample::DownSampleParams {
-                table_name: "memory_battery_status".to_string(),
-                end_time: end,
-                start_time: end - 10,
-                interval_secs: 2,
-                ..Default::default()
-            },
-        )
-        .await;
-        let r2 = r1.as_ref().unwrap();
+        let end = Utc::now().timestamp_millis();
+        let r2 = store.buffer.down_sample(end - 10_000, end, 2000);
         dbg!(Utc::now().timestamp_millis() - now_millis);
         dbg!(r2.len());
         assert!(r2.len() == 5);
     }
+    #[tokio::test]
+    async fn down_sample_aggregations() {
+        use down_sample::*;
+        use sea_orm::*;
+        #[derive(Debug, FromQueryResult)]
+        struct Row {
+            timestamp: i64,
+            first: String,
+            last: String,
+            avg: f32,
+            min: f32,
+            max: f32,
+            sum: f32,
+            count: i32,
+        }
+        let store = BatteryStore::new(10, None).await.unwrap();
+        let rows: Vec<memory_battery_status::ActiveModel> = (0..6)
+            .map(|i| memory_battery_status::ActiveModel {
+                timestamp: Set(100 + i),
+                state: Set(format!("s{}", i)),
+                percentage: Set(i as f32),
+                energy_rate: Set(0.0),
+                voltage: Set(0.0),
+                cpu_load: Set(0.0),
+                ..Default::default()
+            })
+            .collect();
+        memory_battery_status::Entity::insert_many(rows)
+            .exec(store.db.as_ref().unwrap())
+            .await
+            .unwrap();
+        let columns = vec![
+            ColumnSpec::new("state", Aggregation::First).alias("first"),
+            ColumnSpec::new("state", Aggregation::Last).alias("last"),
+            ColumnSpec::new("percentage", Aggregation::Avg).alias("avg"),
+            ColumnSpec::new("percentage", Aggregation::Min).alias("min"),
+            ColumnSpec::new("percentage", Aggregation::Max).alias("max"),
+            ColumnSpec::new("percentage", Aggregation::Sum).alias("sum"),
+            ColumnSpec::new("percentage", Aggregation::Count).alias("count"),
+        ];
+        //(99, 105]按3秒分组：99-101、102-104、105
+        let rows: Vec<Row> = down_sample(
+            store.db.as_ref().unwrap(),
+            &DownSampleParams {
+                columns: columns.clone(),
+                start_time: 99,
+                end_time: 105,
+                interval: 3,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].timestamp, 99);
+        assert_eq!((rows[0].first.as_str(), rows[0].last.as_str()), ("s0", "s1"));
+        assert_eq!(rows[1].timestamp, 102);
+        assert_eq!((rows[1].first.as_str(), rows[1].last.as_str()), ("s2", "s4"));
+        assert_eq!((rows[1].avg, rows[1].min, rows[1].max), (3.0, 2.0, 4.0));
+        assert_eq!((rows[1].sum, rows[1].count), (9.0, 3));
+        assert_eq!((rows[2].timestamp, rows[2].count), (105, 1));
+        //标识符不能拼接任意SQL
+        let invalid = build_down_sample_sql(&DownSampleParams {
+            table_name: "memory_battery_status; DROP TABLE x".to_string(),
+            columns,
+            ..Default::default()
+        });
+        assert!(invalid.is_err());
+    }
+    #[tokio::test]
+    async fn series_with_gaps() {
+        use sea_orm::*;
+        let store = BatteryStore::new(10, None).await.unwrap();
+        let db = store.db.as_ref().unwrap();
+        let now = 1_743_800_000_000 - 1_743_800_000_000 % 60_000;
+        //最近一小时的分钟数据，中间缺失10分钟
+        let rows: Vec<battery_one_minutes::ActiveModel> = (0..60)
+            .filter(|i| !(20..30).contains(i))
+            .map(|i| {
+                battery_one_minutes::Model {
+                    id: i as i32 + 1,
+                    timestamp: now - 3_600_000 + i * 60_000,
+                    state: "discharging".to_string(),
+                    percentage: 80.0,
+                    energy_rate: -8.0,
+                    voltage: 12.0,
+                    cpu_load: 0.2,
+                    temperature: None,
+                    cycle_count: None,
+                    time_to_empty_secs: None,
+                    time_to_full_secs: None,
+                }
+                .into_active_model()
+            })
+            .collect();
+        battery_one_minutes::Entity::insert_many(rows)
+            .exec(db)
+            .await
+            .unwrap();
+        let metrics = vec!["percentage".to_string(), "energy_rate".to_string()];
+        let series = select_series(
+            db,
+            &RetentionPolicy::default(),
+            &metrics,
+            now - 3_600_000,
+            now,
+            60,
+            now,
+        )
+        .await
+        .unwrap();
+        assert_eq!(series.source, "battery_one_minutes");
+        assert_eq!(series.interval_ms, 60_000);
+        let gaps: Vec<&SeriesPoint> = series
+            .points
+            .iter()
+            .filter(|x| matches!(x, SeriesPoint::Gap { .. }))
+            .collect();
+        assert_eq!(
+            gaps,
+            vec![&SeriesPoint::Gap {
+                start: now - 3_600_000 + 19 * 60_000,
+                end: now - 3_600_000 + 30 * 60_000,
+            }]
+        );
+        assert_eq!(
+            series.points[0],
+            SeriesPoint::Value {
+                timestamp: now - 3_600_000,
+                values: vec![Some(80.0), Some(-8.0)],
+            }
+        );
+        //超出分钟数据的保留范围时使用汇总表
+        let series = select_series(
+            db,
+            &RetentionPolicy::default(),
+            &metrics,
+            now - 30 * 86_400_000,
+            now,
+            1000,
+            now,
+        )
+        .await
+        .unwrap();
+        assert_eq!(series.source, "battery_one_hour");
+        let invalid = vec!["state".to_string()];
+        assert!(
+            select_series(db, &RetentionPolicy::default(), &invalid, 0, now, 10, now)
+                .await
+                .is_err()
+        );
+    }
+    #[test]
+    fn drain_model_recommend() {
+        //drain = 2 + 4*load + 0.1*stapm + 0.5*load*stapm
+        let mut samples = Vec::new();
+        for stapm in [8.0, 12.0, 15.0, 20.0, 25.0] {
+            for load in 0..10 {
+                let load = load as f32 / 10.0;
+                samples.push(DrainSample {
+                    cpu_load: load,
+                    stapm_limit: stapm,
+                    drain: 2.0 + 4.0 * load + 0.1 * stapm + 0.5 * load * stapm,
+                });
+            }
+        }
+        let model = DrainModel::fit(&samples).unwrap();
+        assert!(model.r2 > 0.99);
+        assert!((model.predict(0.5, 10.0) - 7.5).abs() < 0.01);
+        //50Wh，load=0.5时stapm=12W为8.2W，续航6.1h；stapm=13W为8.55W，续航5.85h
+        let rec = recommend(&model, power::FAM_RENOIR, 50.0, 6.0, 0.5).unwrap();
+        assert_eq!(rec.limit.stapm_limit, 12.0);
+        assert!(rec.predicted_hours.unwrap() >= 6.0);
+        //stapm从未变化时无法拟合
+        let flat: Vec<DrainSample> = samples
+            .iter()
+            .map(|x| DrainSample {
+                stapm_limit: 15.0,
+                ..*x
+            })
+            .collect();
+        assert!(DrainModel::fit(&flat).is_none());
+    }
+    #[test]
+    fn experiment_stats() {
+        let stats = MetricStats::from_values(&[10.0, 12.0, 14.0]).unwrap();
+        assert_eq!(stats.samples, 3);
+        assert_eq!(stats.mean, 12.0);
+        assert_eq!(stats.std_dev, 2.0);
+        //t(2)=4.303，半宽=4.303*2/sqrt(3)
+        assert!((stats.ci_high - 16.969).abs() < 0.01);
+        assert!((stats.ci_low - 7.031).abs() < 0.01);
+        assert!(MetricStats::from_values(&[10.0]).is_none());
+    }
+    #[test]
+    fn retention_validate() {
+        let policy = RetentionPolicy::default();
+        assert_eq!(policy.validate(), Ok(()));
+        let shorter = RetentionPolicy {
+            one_minute_secs: 2 * 24 * 3600,
+            realtime_secs: 3 * 24 * 3600,
+            ..policy
+        };
+        assert!(shorter.validate().is_err());
+        //有限的保留时长有上限，只有one_day_secs可以永久保留
+        let forever = RetentionPolicy {
+            one_hour_secs: i64::MAX,
+            ..policy
+        };
+        assert!(forever.validate().is_err());
+        let long = RetentionPolicy {
+            one_hour_secs: 5 * 365 * 24 * 3600,
+            ..policy
+        };
+        assert_eq!(long.validate(), Ok(()));
+        let memory = RetentionPolicy {
+            memory_secs: 2 * 3600,
+            realtime_secs: 2 * 3600,
+            ..policy
+        };
+        assert!(memory.validate().is_err());
+    }
+    #[tokio::test]
+    async fn sample_buffer_matches_sql() {
+        use down_sample::*;
+        use sea_orm::*;
+        let store = BatteryStore::new(10, None).await.unwrap();
+        let db = store.db.as_ref().unwrap();
+        let mut buffer = SampleBuffer::new(1000);
+        //每500毫秒一个样本，状态交替变化
+        let mut rows = Vec::new();
+        for i in 0..300i64 {
+            let timestamp = 1_743_800_000_000 + i * 500;
+            let sample = buffer.push(memory_battery_status::Model {
+                id: 0,
+                timestamp,
+                state: ["charging", "discharging", "full"][(i % 7 % 3) as usize].to_string(),
+                percentage: 50.0 + (i as f32 * 0.37).sin() * 10.0,
+                energy_rate: -8.0 - (i % 11) as f32 * 0.13,
+                voltage: 11.9 + (i % 5) as f32 * 0.01,
+                cpu_load: (i % 13) as f32 / 13.0,
+                temperature: match i % 4 {
+                    0 => None,
+                    _ => Some(30.0 + (i % 9) as f32 * 0.5),
+                },
+                cycle_count: Some(100 + (i / 50) as i32),
+                time_to_empty_secs: Some(3600 - i * 10),
+                time_to_full_secs: None,
+            });
+            rows.push(sample.into_active_model());
+        }
+        memory_battery_status::Entity::insert_many(rows)
+            .exec(db)
+            .await
+            .unwrap();
+        for (start, end, interval) in [
+            (1_743_799_990_000, 1_743_800_010_000, 2000),
+            (1_743_800_031_000, 1_743_800_077_500, 2000),
+            (1_743_800_000_000, 1_743_800_200_000, 60_000),
+        ] {
+            let expected: Vec<BatterySample> = down_sample(
+                db,
+                &DownSampleParams {
+                    start_time: start,
+                    end_time: end,
+                    interval,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+            let actual = buffer.down_sample(start, end, interval);
+            assert_eq!(actual.len(), expected.len());
+            for (a, e) in actual.iter().zip(expected.iter()) {
+                assert_eq!((a.timestamp, &a.state), (e.timestamp, &e.state));
+                assert_eq!(
+                    (a.cycle_count, a.time_to_empty_secs, a.time_to_full_secs),
+                    (e.cycle_count, e.time_to_empty_secs, e.time_to_full_secs)
+                );
+                match (a.temperature, e.temperature) {
+                    (Some(x), Some(y)) => assert!((x - y).abs() <= 1e-5, "{} != {}", x, y),
+                    (x, y) => assert_eq!(x, y),
+                }
+                for (x, y) in [
+                    (a.percentage, e.percentage),
+                    (a.energy_rate, e.energy_rate),
+                    (a.voltage, e.voltage),
+                    (a.cpu_load, e.cpu_load),
+                ] {
+                    assert!((x - y).abs() <= 1e-5, "{} != {}", x, y);
+                }
+            }
+        }
+        assert_eq!(buffer.clean(1_743_800_100_000), 200);
+        assert_eq!(buffer.len(), 100);
+    }
+    #[tokio::test]
+    async fn recover_dangling_history() {
+        use sea_orm::*;
+        let path = env::temp_dir().join("persis_recover_test.db");
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.to_str().unwrap(), suffix));
+        }
+        let conn_str = format!("sqlite://{}?mode=rwc", path.to_str().unwrap());
+        let store = BatteryStore::new(10, Some(conn_str.clone())).await.unwrap();
+        let db = store.db.as_ref().unwrap();
+        for (timestamp, state) in [(1000, "charging"), (2000, "discharging")] {
+            battery_state_history::ActiveModel {
+                id: NotSet,
+                timestamp: Set(timestamp),
+                state: Set(state.to_string()),
+                capacity: Set(50.0),
+                full_capacity: Set(60.0),
+                design_capacity: Set(60.0),
+                percentage: Set(80.0),
+                state_of_health: Set(1.0),
+                energy_rate: Set(-8.0),
+                voltage: Set(12.0),
+                cpu_load: Set(0.1),
+                temperature: Set(None),
+                cycle_count: Set(None),
+                time_to_empty_secs: Set(None),
+                time_to_full_secs: Set(None),
+                prev: Set(None),
+                end_at: Set(None),
+            }
+            .insert(db)
+            .await
+            .unwrap();
+        }
+        battery_realtime::Model {
+            id: 1,
+            timestamp: 2345,
+            state: "discharging".to_string(),
+            percentage: 79.0,
+            energy_rate: -8.0,
+            voltage: 12.0,
+            cpu_load: 0.1,
+            temperature: None,
+            cycle_count: None,
+            time_to_empty_secs: None,
+            time_to_full_secs: None,
+        }
+        .into_active_model()
+        .insert(db)
+        .await
+        .unwrap();
+        db.clone().close().await.unwrap();
+        //模拟异常退出后重新启动
+        let store = BatteryStore::new(10, Some(conn_str)).await.unwrap();
+        let rows = battery_state_history::Entity::find()
+            .order_by_asc(battery_state_history::Column::Timestamp)
+            .all(store.db.as_ref().unwrap())
+            .await
+            .unwrap();
+        let end_at: Vec<Option<i64>> = rows.iter().map(|x| x.end_at).collect();
+        assert_eq!(end_at, vec![Some(2000), Some(2345)]);
+    }
+    #[tokio::test]
+    async fn rollup_hour_and_day() {
+        use sea_orm::*;
+        let store = BatteryStore::new(10, None).await.unwrap();
+        let db = store.db.as_ref().unwrap();
+        let hour = 1_743_724_800_000;
+        let rows: Vec<battery_one_minutes::ActiveModel> = (0..3)
+            .map(|i| {
+                battery_one_minutes::Model {
+                    id: i as i32 + 1,
+                    timestamp: hour + i * 60_000,
+                    state: "discharging".to_string(),
+                    percentage: 90.0 - i as f32,
+                    energy_rate: -10.0 - i as f32,
+                    voltage: 12.0,
+                    cpu_load: 0.1 * (i + 1) as f32,
+                    temperature: None,
+                    cycle_count: None,
+                    time_to_empty_secs: None,
+                    time_to_full_secs: None,
+                }
+                .into_active_model()
+            })
+            .collect();
+        battery_one_minutes::Entity::insert_many(rows)
+            .exec(db)
+            .await
+            .unwrap();
+        //重复执行不产生重复数据
+        for _ in 0..2 {
+            rollup::update_rollups(db, &[hour, hour + 120_000]).await.unwrap();
+        }
+        let rows = battery_one_hour::Entity::find().all(db).await.unwrap();
+        assert_eq!(rows.len(), 1);
+        let row = &rows[0];
+        assert_eq!(row.timestamp, hour);
+        assert_eq!(row.samples, 3);
+        assert_eq!(row.percentage_min, 88.0);
+        assert_eq!(row.percentage_max, 90.0);
+        assert_eq!(row.percentage_last, 88.0);
+        assert_eq!(row.energy_rate_avg, -11.0);
+        let day = battery_one_day::Entity::find().one(db).await.unwrap().unwrap();
+        assert_eq!(day.timestamp, hour - hour % 86_400_000);
+        assert_eq!(day.samples, 3);
+    }
+    #[tokio::test]
+    async fn surrogate_key_migration() {
+        use migration::MigratorTrait;
+        use sea_orm::*;
+        //旧版本的数据库：timestamp为主键，单位为秒
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        migration::Migrator::up(&db, Some(6)).await.unwrap();
+        for sql in [
+            r#"INSERT INTO "battery_realtime" ("timestamp", "state", "percentage", "energy_rate", "voltage", "cpu_load") VALUES (1000, 'charging', 0.5, 10.0, 12.0, 0.1), (1002, 'charging', 0.6, 10.0, 12.0, 0.1)"#,
+            r#"INSERT INTO "battery_state_history" ("timestamp", "state", "end_at", "capacity", "full_capacity", "design_capacity", "percentage", "state_of_health", "energy_rate", "voltage", "cpu_load") VALUES (900, 'charging', 1002, 50.0, 60.0, 60.0, 0.5, 1.0, 10.0, 12.0, 0.1), (1002, 'full', NULL, 60.0, 60.0, 60.0, 1.0, 1.0, 0.0, 12.0, 0.1)"#,
+            r#"INSERT INTO "power_one_minutes" ("timestamp", "stapm_limit", "stapm_value", "slow_limit", "slow_value", "fast_limit", "fast_value") VALUES (960, 15.0, 10.0, 20.0, 12.0, 25.0, 14.0)"#,
+        ] {
+            db.execute_unprepared(sql).await.unwrap();
+        }
+        migration::Migrator::up(&db, None).await.unwrap();
+        let rows = battery_realtime::Entity::find()
+            .order_by_asc(battery_realtime::Column::Id)
+            .all(&db)
+            .await
+            .unwrap();
+        let rows: Vec<(i32, i64)> = rows.iter().map(|x| (x.id, x.timestamp)).collect();
+        assert_eq!(rows, vec![(1, 1_000_000), (2, 1_002_000)]);
+        let rows = battery_state_history::Entity::find()
+            .order_by_asc(battery_state_history::Column::Id)
+            .all(&db)
+            .await
+            .unwrap();
+        let rows: Vec<(i32, i64, Option<i64>)> =
+            rows.iter().map(|x| (x.id, x.timestamp, x.end_at)).collect();
+        assert_eq!(
+            rows,
+            vec![(1, 900_000, Some(1_002_000)), (2, 1_002_000, None)]
+        );
+        //功率表同样换算为毫秒
+        let power = power_one_minutes::Entity::find().one(&db).await.unwrap();
+        assert_eq!(power.unwrap().timestamp, 960_000);
+        //同一毫秒的分组只能有一条
+        let duplicate = db
+            .execute_unprepared(r#"INSERT INTO "battery_realtime" ("timestamp", "state", "percentage", "energy_rate", "voltage", "cpu_load") VALUES (1000000, 'charging', 0.5, 10.0, 12.0, 0.1)"#)
+            .await;
+        assert!(duplicate.is_err());
+    }
+    #[tokio::test]
+    async fn merge_micro_segments() {
+        use sea_orm::*;
+        let store = BatteryStore::new(10, None).await.unwrap();
+        let db = store.db.as_ref().unwrap();
+        //充电-满电抖动-充电-满电，中间的短段合并后与前后的充电段合为一段
+        let segments = [
+            (0, "charging", Some(600_000), 0.8, 10.0),
+            (600_000, "full", Some(605_000), 0.85, 0.0),
+            (605_000, "charging", Some(900_000), 0.9, 20.0),
+            (900_000, "full", Some(3_600_000), 1.0, 0.0),
+            (3_600_000, "discharging", None, 1.0, -10.0),
+        ];
+        for (timestamp, state, end_at, percentage, energy_rate) in segments {
+            battery_state_history::ActiveModel {
+                timestamp: Set(timestamp),
+                state: Set(state.to_string()),
+                end_at: Set(end_at),
+                capacity: Set(50.0),
+                full_capacity: Set(60.0),
+                design_capacity: Set(60.0),
+                percentage: Set(percentage),
+                state_of_health: Set(1.0),
+                energy_rate: Set(energy_rate),
+                voltage: Set(12.0),
+                cpu_load: Set(0.1),
+                ..Default::default()
+            }
+            .insert(db)
+            .await
+            .unwrap();
+        }
+        assert_eq!(segments::merge_micro_segments(db, 30_000).await.unwrap(), 2);
+        let rows = battery_state_history::Entity::find()
+            .order_by_asc(battery_state_history::Column::Timestamp)
+            .all(db)
+            .await
+            .unwrap();
+        let rows: Vec<(i64, &str, Option<&str>, Option<i64>)> = rows
+            .iter()
+            .map(|x| (x.timestamp, x.state.as_str(), x.prev.as_deref(), x.end_at))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (0, "charging", None, Some(900_000)),
+                (900_000, "full", Some("charging"), Some(3_600_000)),
+                (3_600_000, "discharging", Some("full"), None),
+            ]
+        );
+        //合并后的段取最后一段结束时的电量，平均功率按时长加权
+        let first = battery_state_history::Entity::find()
+            .order_by_asc(battery_state_history::Column::Timestamp)
+            .one(db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.percentage, 0.9);
+        assert!((first.energy_rate - (10.0 * 600.0 + 20.0 * 295.0) / 900.0).abs() < 1e-3);
+        //重复执行不再变化
+        assert_eq!(segments::merge_micro_segments(db, 30_000).await.unwrap(), 0);
+    }
+    #[tokio::test]
+    async fn sleep_detect_and_drain() {
+        let mut detector = SleepDetector::new(30_000);
+        assert_eq!(detector.tick(1_000_000, 0), None);
+        //正常运行，系统时间与单调时钟同步
+        assert_eq!(detector.tick(1_001_000, 1_000), None);
+        assert_eq!(detector.tick(1_012_000, 12_000), None);
+        //单调时钟只走了1秒，系统时间走了2小时
+        assert_eq!(
+            detector.tick(1_012_000 + 7_200_000, 13_000),
+            Some((1_013_000, 1_012_000 + 7_200_000))
+        );
+        assert_eq!(detector.tick(1_012_000 + 7_201_000, 14_000), None);
+        //主循环卡顿1小时，两个时钟同步前进，不是睡眠
+        assert_eq!(detector.tick(1_012_000 + 10_801_000, 3_614_000), None);
+        let store = BatteryStore::new(10, None).await.unwrap();
+        let db = store.db.as_ref().unwrap();
+        let session = SleepSession {
+            start_at: 0,
+            end_at: 8 * 3_600_000,
+            start_percentage: 0.8,
+            end_percentage: 0.64,
+            start_capacity: 40.0,
+            end_capacity: 32.0,
+            full_capacity: 50.0,
+        };
+        assert_eq!(session.drain_rate(), 1.0);
+        assert!((session.percentage_rate() - 0.02).abs() < 1e-6);
+        insert_sleep_session(db, &session).await.unwrap();
+        let rows = select_sleep_sessions(db, 0, 3_600_000).await.unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].drain_rate, 1.0);
+    }
+    #[tokio::test]
+    async fn software_cycle_count() {
+        let store = BatteryStore::new(10, None).await.unwrap();
+        let db = store.db.as_ref().unwrap();
+        let discharging = battery::State(battery::ExternalBatteryState::Discharging);
+        let charging = battery::State(battery::ExternalBatteryState::Charging);
+        let mut counter = load_cycle_counter(db, "a/b/c").await.unwrap();
+        //放电按当时的满充容量折算，充电期间的波动不计入
+        counter.observe(discharging, 50.0, 50.0, None, 0);
+        counter.observe(discharging, 30.0, 50.0, None, 1);
+        counter.observe(charging, 50.0, 50.0, None, 2);
+        counter.observe(charging, 49.0, 50.0, None, 3);
+        counter.observe(discharging, 29.0, 40.0, None, 4);
+        assert!((counter.discharged_wh - 40.0).abs() < 1e-6);
+        assert!((counter.cycles - 0.9).abs() < 1e-6);
+        assert_eq!(counter.wear_per_100_cycles(0.95), None);
+        save_cycle_counter(db, &mut counter).await.unwrap();
+        //重新读取后，关闭期间的放电也计入
+        let mut counter = load_cycle_counter(db, "a/b/c").await.unwrap();
+        counter.observe(discharging, 19.0, 40.0, Some(3), 5);
+        assert!((counter.discharged_wh - 50.0).abs() < 1e-6);
+        assert!((counter.cycles - 1.15).abs() < 1e-6);
+        let health = counter.health(0.94);
+        assert_eq!(health.effective_cycles, 3.0);
+        assert!((health.wear_per_100_cycles.unwrap() - 2.0).abs() < 1e-4);
+        save_cycle_counter(db, &mut counter).await.unwrap();
+        let rows = select_battery_cycles(db).await.unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].driver_cycle_count, Some(3));
+    }
+    #[test]
+    fn full_jitter_no_cycles() {
+        let full = battery::State(battery::ExternalBatteryState::Full);
+        let unknown = battery::State(battery::ExternalBatteryState::Unknown);
+        let mut counter = CycleCounter::new("a/b/c".to_string());
+        //满电及未知状态下容量的抖动不计入放电
+        for (i, (state, capacity)) in [
+            (full, 50.0),
+            (unknown, 49.5),
+            (full, 50.0),
+            (unknown, 49.2),
+            (full, 49.0),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            counter.observe(state, capacity, 50.0, None, i as i64);
+        }
+        assert_eq!(counter.discharged_wh, 0.0);
+        assert_eq!(counter.cycles, 0.0);
+    }
+    #[tokio::test]
+    async fn stress_histogram() {
+        let store = BatteryStore::new(10, None).await.unwrap();
+        let db = store.db.as_ref().unwrap();
+        let rows: Vec<String> = [
+            (0.95, "36.0"),
+            (0.95, "36.0"),
+            (0.95, "36.0"),
+            (0.5, "NULL"),
+            (0.1, "20.0"),
+        ]
+        .iter()
+        .enumerate()
+        .map(|(i, (percentage, temperature))| {
+            format!(
+                "({}, 'discharging', {}, -10.0, 12.0, 0.1, {})",
+                (i as i64 + 1) * 60_000,
+                percentage,
+                temperature
+            )
+        })
+        .collect();
+        db.execute_unprepared(&format!(
+            r#"INSERT INTO "battery_one_minutes" ("timestamp", "state", "percentage", "energy_rate", "voltage", "cpu_load", "temperature") VALUES {}"#,
+            rows.join(", ")
+        ))
+        .await
+        .unwrap();
+        //增量统计，重复执行不重复计入
+        assert_eq!(update_stress(db, 240_000).await.unwrap(), 4);
+        assert_eq!(update_stress(db, 240_000).await.unwrap(), 0);
+        assert_eq!(update_stress(db, 300_000).await.unwrap(), 1);
+        let histogram = select_stress(db).await.unwrap();
+        assert_eq!(histogram.total_secs, 300);
+        assert_eq!(histogram.bins.len(), 3);
+        assert_eq!(histogram.soc_secs[5], 180);
+        assert_eq!(histogram.temp_secs[TEMP_BANDS.len()], 60);
+        assert_eq!(histogram.updated_at, 300_000);
+        assert!((histogram.high_soc_hot_share - 0.6).abs() < 1e-9);
+        assert!((histogram.share(0.9, None) - 0.6).abs() < 1e-9);
+        assert_eq!(histogram.share(0.9, Some(45.0)), 0.0);
+        assert!((histogram.aging_rate - 3.278).abs() < 1e-6);
+        assert!((histogram.stress_score - 64.65).abs() < 0.01);
+        //睡眠2小时按平均电量80%计入，不影响分钟样本的统计进度
+        let session = SleepSession {
+            start_at: 300_000,
+            end_at: 300_000 + 7_200_000,
+            start_percentage: 0.85,
+            end_percentage: 0.75,
+            start_capacity: 42.5,
+            end_capacity: 37.5,
+            full_capacity: 50.0,
+        };
+        add_sleep_stress(db, &session).await.unwrap();
+        let histogram = select_stress(db).await.unwrap();
+        assert_eq!(histogram.total_secs, 7500);
+        assert_eq!(histogram.soc_secs[4], 7200);
+        assert_eq!(histogram.temp_secs[TEMP_BANDS.len()], 7260);
+        assert_eq!(histogram.updated_at, 300_000);
+        assert_eq!(update_stress(db, 300_000).await.unwrap(), 0);
+    }
+    #[tokio::test]
+    async fn health_score_and_alerts() {
+        let inputs = HealthInputs {
+            state_of_health: 1.0,
+            ..Default::default()
+        };
+        assert_eq!(inputs.score(), 100.0);
+        let inputs = HealthInputs {
+            state_of_health: 0.8,
+            fade_per_year: Some(0.05),
+            cycles: Some(500.0),
+            stress_score: Some(50.0),
+            anomalies: 0,
+        };
+        assert!((inputs.score() - 55.0).abs() < 1e-4);
+        let store = BatteryStore::new(10, None).await.unwrap();
+        let db = store.db.as_ref().unwrap();
+        let now = DAY_MS * 100;
+        //睡眠1小时耗电20%，计为1个异常
+        insert_sleep_session(
+            db,
+            &SleepSession {
+                start_at: now - 3_600_000,
+                end_at: now,
+                start_percentage: 0.8,
+                end_percentage: 0.6,
+                start_capacity: 40.0,
+                end_capacity: 30.0,
+                full_capacity: 50.0,
+            },
+        )
+        .await
+        .unwrap();
+        let first = update_health(db, "k", 0.92, Some(100.0), now)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.current.anomalies, 1);
+        assert!((first.current.score - 82.31).abs() < 0.01);
+        assert!(first.alerts(&[80.0], &[0.9]).is_empty());
+        //同一天不重复计算
+        assert!(update_health(db, "k", 0.9, Some(100.0), now + 1000)
+            .await
+            .unwrap()
+            .is_none());
+        let second = update_health(db, "k", 0.88, Some(100.0), now + DAY_MS)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(second.previous, Some(first.current));
+        assert_eq!(second.alerts(&[80.0, 60.0], &[0.9, 0.8]).len(), 2);
+        let rows = select_health(db, "k", 10).await.unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].day, now + DAY_MS);
+        //换电池后，之前的睡眠异常不计入新电池
+        let discharging = battery::State(battery::ExternalBatteryState::Discharging);
+        let mut counter = CycleCounter::new("k".to_string());
+        counter.observe(discharging, 40.0, 50.0, None, now + DAY_MS);
+        save_cycle_counter(db, &mut counter).await.unwrap();
+        let other = update_health(db, "k2", 0.99, None, now + 2 * DAY_MS)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(other.previous, None);
+        assert_eq!(other.current.anomalies, 0);
+    }
+    #[tokio::test]
+    async fn power_audit_full_limit() {
+        let store = BatteryStore::new(10, None).await.unwrap();
+        let db = store.db.as_ref().unwrap();
+        let before = power::PowerLimit {
+            stapm_limit: 15.0,
+            slow_limit: 20.0,
+            fast_limit: 25.0,
+            ..Default::default()
+        };
+        let request = power::PowerLimit {
+            tctl_temp: Some(80),
+            coall: Some(-10),
+            ..before
+        };
+        let audit = PowerAudit {
+            timestamp: 1000,
+            source: PowerAuditSource::User,
+            detail: None,
+            before,
+            request,
+            after: Some(request),
+            success: true,
+            error: None,
+        };
+        insert_power_audit(db, &audit).await.unwrap();
+        let rows = select_power_audit_page(db, None, 10).await.unwrap();
+        assert_eq!(rows[0].request_stapm, 15.0);
+        //扩展参数也被记录
+        let limit = rows[0].request_limit.as_ref().unwrap();
+        assert_eq!(limit["tctl_temp"], 80);
+        assert_eq!(limit["coall"], -10);
+        let before = rows[0].before_limit.as_ref().unwrap();
+        assert_eq!(before["tctl_temp"], serde_json::Value::Null);
+    }
+    //设置UPDATE_SNAPSHOTS环境变量时重新生成快照
+    fn assert_snapshot(name: &str, actual: &str) {
+        let path = format!("{}/snapshots/{}", env!("CARGO_MANIFEST_DIR"), name);
+        if env::var("UPDATE_SNAPSHOTS").is_ok() {
+            std::fs::write(&path, actual).unwrap();
+        }
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), actual);
+    }
+    #[tokio::test]
+    async fn battery_report_snapshot() {
+        let store = BatteryStore::new(10, None).await.unwrap();
+        let db = store.db.as_ref().unwrap();
+        let hour = 3_600_000;
+        let now = DAY_MS * 20000 + 12 * hour;
+        //相对now的小时数
+        let rows = [
+            (-8 * 24, "discharging", Some(-8 * 24 + 1), 30.0, 56.0, 0.55, -10.0),
+            (-6, "charging", Some(-4), 50.0, 55.0, 0.9, 20.0),
+            (-4, "full", Some(-3), 55.0, 55.0, 1.0, 0.0),
+            (-3, "discharging", Some(-1), 35.0, 55.0, 0.64, -10.0),
+            (-1, "charging", None, 45.0, 55.0, 0.82, 15.0),
+        ]
+        .map(|(start, state, end, capacity, full, percentage, rate)| {
+            let end = end.map_or("NULL".to_string(), |x| (now + x * hour).to_string());
+            format!(
+                "({}, '{state}', {end}, {capacity}, {full}, 60.0, {percentage}, 0.917, {rate}, 12.0, 0.1)",
+                now + start * hour
+            )
+        });
+        db.execute_unprepared(&format!(
+            r#"INSERT INTO "battery_state_history" ("timestamp", "state", "end_at", "capacity", "full_capacity", "design_capacity", "percentage", "state_of_health", "energy_rate", "voltage", "cpu_load") VALUES {}"#,
+            rows.join(", ")
+        ))
+        .await
+        .unwrap();
+        insert_sleep_session(
+            db,
+            &SleepSession {
+                start_at: now - 10 * hour,
+                end_at: now - 8 * hour,
+                start_percentage: 0.8,
+                end_percentage: 0.78,
+                start_capacity: 44.0,
+                end_capacity: 43.0,
+                full_capacity: 55.0,
+            },
+        )
+        .await
+        .unwrap();
+        let key = "SMP/L19/123";
+        let discharging = battery::State(battery::ExternalBatteryState::Discharging);
+        let mut counter = load_cycle_counter(db, key).await.unwrap();
+        counter.observe(discharging, 50.0, 55.0, None, now - 3 * hour);
+        counter.observe(discharging, 28.0, 55.0, None, now - hour);
+        save_cycle_counter(db, &mut counter).await.unwrap();
+        update_health(db, key, 0.917, Some(counter.cycles), now)
+            .await
+            .unwrap();
+        let identity = ReportIdentity {
+            battery_key: key.to_string(),
+            vendor: Some("SMP".to_string()),
+            model: Some("L19".to_string()),
+            serial_number: Some("123".to_string()),
+            design_capacity: 60.0,
+            full_capacity: 55.0,
+            capacity: 45.0,
+            state_of_health: 0.917,
+            cycle_count: None,
+        };
+        let report = BatteryReport::build(db, identity.clone(), now)
+            .await
+            .unwrap();
+        assert_eq!(report.runtime.full_hours, Some(5.5));
+        //相同的数据和时间生成相同的报告
+        let again = BatteryReport::build(db, identity, now).await.unwrap();
+        assert_eq!(report, again);
+        assert_snapshot("battery_report.md", &report.to_markdown());
+        assert_snapshot("battery_report.html", &report.to_html());
+    }
+    #[tokio::test]
+    async fn battery_report_read_only() {
+        use sea_orm::*;
+        let path = env::temp_dir().join("persis_report_test.db");
+        let output = env::temp_dir().join("persis_report_test.md");
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&output);
+        let conn_str = format!("sqlite://{}?mode=rwc", path.to_str().unwrap());
+        let db = Database::connect(&conn_str).await.unwrap();
+        migration::Migrator::up(&db, Some(3)).await.unwrap();
+        //表结构落后时报错，不执行迁移
+        let now = DAY_MS * 20000;
+        let err = write_battery_report(&path, ReportIdentity::default(), &output, now)
+            .await
+            .unwrap_err();
+        assert!(err.contains("out of date"));
+        assert_eq!(
+            migration::Migrator::get_pending_migrations(&db)
+                .await
+                .unwrap()
+                .len(),
+            migration::Migrator::migrations().len() - 3
+        );
+        migration::Migrator::up(&db, None).await.unwrap();
+        db.close().await.unwrap();
+        write_battery_report(&path, ReportIdentity::default(), &output, now)
+            .await
+            .unwrap();
+        assert!(std::fs::read_to_string(&output).unwrap().starts_with('#'));
+    }
+    #[tokio::test]
+    async fn power_range_follows_retention() {
+        use sea_orm::*;
+        let path = env::temp_dir().join("persis_power_range_test.db");
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.to_str().unwrap(), suffix));
+        }
+        let mut manager = manager::Manager::build(&path.to_str().unwrap().to_string(), 10)
+            .await
+            .unwrap();
+        manager
+            .set_retention(RetentionPolicy {
+                realtime_secs: 3600,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let db = Database::connect(format!("sqlite://{}?mode=rw", path.to_str().unwrap()))
+            .await
+            .unwrap();
+        let now = Utc::now().timestamp_millis();
+        let hour = 3_600_000;
+        //realtime与one_minutes用不同的stapm_value区分
+        for (table, timestamp, stapm_value) in [
+            ("power_realtime", now - 3 * hour, 1.0),
+            ("power_one_minutes", now - 3 * hour, 2.0),
+            ("power_realtime", now - hour / 2, 3.0),
+            ("power_one_minutes", now - hour / 2, 4.0),
+        ] {
+            db.execute_unprepared(&format!(
+                r#"INSERT INTO "{table}" ("timestamp", "stapm_limit", "stapm_value", "slow_limit", "slow_value", "fast_limit", "fast_value") VALUES ({timestamp}, 15.0, {stapm_value}, 20.0, 0.0, 25.0, 0.0)"#
+            ))
+            .await
+            .unwrap();
+        }
+        //超出realtime保留时长的查询改用power_one_minutes
+        let rows = manager
+            .select_power_range(now - 3 * hour - 60_000, now - 2 * hour)
+            .await
+            .unwrap();
+        let values: Vec<f32> = rows.iter().map(|x| x.stapm_value).collect();
+        assert_eq!(values, vec![2.0]);
+        let rows = manager
+            .select_power_range(now - hour + 60_000, now)
+            .await
+            .unwrap();
+        let values: Vec<f32> = rows.iter().map(|x| x.stapm_value).collect();
+        assert_eq!(values, vec![3.0]);
+        manager.close().await;
+    }
+    #[tokio::test]
+    async fn power_samples_merge_from_buffer() {
+        use sea_orm::*;
+        let store = BatteryStore::new(10, None).await.unwrap();
+        let db = store.db.clone().unwrap();
+        let mut power_store = power_store::PowerStore::new(db.clone(), 10);
+        //从下一分钟开始每秒一个样本，stapm_value等于样本序号
+        let base = (Utc::now().timestamp() / 60 + 1) * 60;
+        for i in 0..120 {
+            power_store
+                .insert(&power::Status {
+                    timestamp: base + i,
+                    stapm_limit: 15.0,
+                    stamp_value: i as f32,
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+        power_store.flush(&((base + 120) * 1000)).await.unwrap();
+        let realtime = power_realtime::Entity::find()
+            .order_by_asc(power_realtime::Column::Timestamp)
+            .all(&db)
+            .await
+            .unwrap();
+        assert_eq!(realtime.len(), 60);
+        assert_eq!(realtime[0].timestamp, base * 1000);
+        assert_eq!(realtime[0].stapm_value, 0.5);
+        assert_eq!(realtime[59].stapm_value, 118.5);
+        let values: Vec<(i64, f32)> = power_one_minutes::Entity::find()
+            .order_by_asc(power_one_minutes::Column::Timestamp)
+            .all(&db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|x| (x.timestamp, x.stapm_value))
+            .collect();
+        assert_eq!(values, vec![(base * 1000, 29.5), ((base + 60) * 1000, 89.5)]);
+    }
 }
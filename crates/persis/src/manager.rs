@@ -1,15 +1,45 @@
 use std::{fs::create_dir, path::Path};
 
-use crate::{entities::*, store};
+use crate::{
+    audit::{self, PowerAudit},
+    cycles::{self, CycleHealth},
+    drain_model::{self, DrainModel, PowerRecommendation},
+    entities::*,
+    experiment::{self, ExperimentReport},
+    health::{self, HealthUpdate},
+    report::BatteryReport,
+    retention::{RetentionPolicy, secs_to_ms},
+    segments,
+    series::{self, Series},
+    sleep::{self, SleepSession},
+    store,
+    stress::{self, StressHistogram},
+};
 use sea_orm::{DbErr, EntityTrait, FromQueryResult, Statement};
 use serde::*;
 
-use crate::{battery_realtime, store::BatteryStore};
+use crate::{battery_realtime, power_store::PowerStore, store::BatteryStore};
 pub struct Manager {
     store: BatteryStore,
+    power_store: PowerStore,
+}
+//查询跨度在此范围内且仍在realtime的保留时长内时使用realtime表
+const POWER_REALTIME_SPAN_MS: i64 = 2 * 60 * 60 * 1000;
+//查询跨度(毫秒)超过此值时使用battery_one_day
+const BATTERY_ONE_HOUR_SPAN_MS: i64 = 31 * 24 * 60 * 60 * 1000;
+#[derive(Serialize, Deserialize, Clone, Debug, FromQueryResult)]
+pub struct PowerPoint {
+    pub timestamp: i64,
+    pub stapm_limit: f32,
+    pub stapm_value: f32,
+    pub slow_limit: f32,
+    pub slow_value: f32,
+    pub fast_limit: f32,
+    pub fast_value: f32,
 }
 #[derive(Serialize, Deserialize, Clone, Debug, FromQueryResult)]
 pub struct HistoryInfo {
+    id: i32,
     prev_timestamp: Option<i64>,
     timestamp_diff: Option<i64>,
 
@@ -36,6 +66,11 @@ pub struct HistoryInfo {
     energy_rate: f32,
     voltage: f32,
     cpu_load: f32,
+
+    temperature: Option<f32>,
+    cycle_count: Option<i32>,
+    time_to_empty_secs: Option<i64>,
+    time_to_full_secs: Option<i64>,
 }
 impl Manager {
     pub async fn build(db_path: &String, interval_secs: u32) -> Result<Self, String> {
@@ -46,11 +81,11 @@ impl Manager {
             }
         }
         let conn_str = format!("sqlite://{}?mode=rwc", path.to_str().unwrap());
-        let store = BatteryStore::new(interval_secs, Some(conn_str)).await;
-        match store {
-            Ok(v) => Ok(Self { store: v }),
-            Err(e) => Err(e.to_string()),
-        }
+        let store = BatteryStore::new(interval_secs, Some(conn_str))
+            .await
+            .map_err(|e| e.to_string())?;
+        let power_store = PowerStore::new(store.db.clone().unwrap(), interval_secs);
+        Ok(Self { store, power_store })
     }
     pub async fn insert_battery<F>(
         &mut self,
@@ -59,22 +94,223 @@ impl Manager {
         f: F,
     ) -> Result<Vec<store::InsertModifyed>, DbErr>
     where
-        F: AsyncFnOnce(i64) -> (),
+        F: AsyncFnOnce(i32) -> (),
     {
         let (vec, _model) = self.store.insert(battery, system, f).await?;
         Ok(vec)
     }
+    //校验并应用新的保留策略，立即清理超出范围的数据
+    pub async fn set_retention(&mut self, retention: RetentionPolicy) -> Result<(), String> {
+        retention.validate()?;
+        let now = chrono::Utc::now();
+        self.store
+            .set_retention(retention, &now.timestamp_millis())
+            .await
+            .map_err(|e| e.to_string())?;
+        self.power_store
+            .set_retention(retention, &now.timestamp_millis())
+            .await
+            .map_err(|e| e.to_string())
+    }
+    pub async fn insert_power(&mut self, power: &power::Status) -> Result<(), DbErr> {
+        self.power_store.insert(power).await?;
+        Ok(())
+    }
+    //根据查询跨度(毫秒)选择power_realtime或power_one_minutes
+    pub async fn select_power_range(&self, start: i64, end: i64) -> Result<Vec<PowerPoint>, DbErr> {
+        let db = self.store.db.as_ref().unwrap();
+        let now = chrono::Utc::now().timestamp_millis();
+        let table = match end - start <= POWER_REALTIME_SPAN_MS
+            && start >= now - secs_to_ms(self.power_store.retention.realtime_secs)
+        {
+            true => "power_realtime",
+            false => "power_one_minutes",
+        };
+        PowerPoint::find_by_statement(Statement::from_sql_and_values(
+            sea_orm::DatabaseBackend::Sqlite,
+            format!(
+                r#"
+SELECT * FROM "{table}"
+WHERE "timestamp" BETWEEN $1 AND $2
+ORDER BY "timestamp"
+"#
+            ),
+            [start.into(), end.into()],
+        ))
+        .all(db)
+        .await
+    }
+    //长时间范围(毫秒)的电池数据，根据跨度选择battery_one_hour或battery_one_day
+    pub async fn select_battery_rollup(
+        &self,
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<battery_one_hour::Model>, DbErr> {
+        let db = self.store.db.as_ref().unwrap();
+        let now = chrono::Utc::now().timestamp_millis();
+        let table = match end - start <= BATTERY_ONE_HOUR_SPAN_MS
+            && start >= now - secs_to_ms(self.store.retention.one_hour_secs)
+        {
+            true => "battery_one_hour",
+            false => "battery_one_day",
+        };
+        battery_one_hour::Model::find_by_statement(Statement::from_sql_and_values(
+            sea_orm::DatabaseBackend::Sqlite,
+            format!(
+                r#"
+SELECT * FROM "{table}"
+WHERE "timestamp" BETWEEN $1 AND $2
+ORDER BY "timestamp"
+"#
+            ),
+            [start.into(), end.into()],
+        ))
+        .all(db)
+        .await
+    }
+    //图表使用的时间序列，max_points限制返回的点数，缺失的时间段以Gap标记
+    pub async fn series(
+        &self,
+        metrics: &[String],
+        start: i64,
+        end: i64,
+        max_points: u32,
+    ) -> Result<Series, DbErr> {
+        series::select_series(
+            self.store.db.as_ref().unwrap(),
+            &self.store.retention,
+            metrics,
+            start,
+            end,
+            max_points,
+            chrono::Utc::now().timestamp_millis(),
+        )
+        .await
+    }
+    pub async fn insert_power_audit(&self, audit: &PowerAudit) -> Result<i32, DbErr> {
+        audit::insert_power_audit(self.store.db.as_ref().unwrap(), audit).await
+    }
+    pub async fn select_power_audit_page(
+        &self,
+        cursor: Option<i32>,
+        size: u8,
+    ) -> Result<Vec<power_limit_audit::Model>, DbErr> {
+        audit::select_power_audit_page(self.store.db.as_ref().unwrap(), cursor, size as u64).await
+    }
+    //用battery_one_minutes中保留的放电数据拟合模型，推荐达到目标续航的功率
+    pub async fn recommend_power_limit(
+        &self,
+        cpu_family: i32,
+        capacity_wh: f32,
+        target_hours: f32,
+        now: i64,
+    ) -> Result<Option<PowerRecommendation>, DbErr> {
+        let db = self.store.db.as_ref().unwrap();
+        let start = now - secs_to_ms(self.store.retention.one_minute_secs);
+        let samples = drain_model::select_drain_samples(db, start, now).await?;
+        let model = match DrainModel::fit(&samples) {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+        let typical_load = samples.iter().map(|x| x.cpu_load).sum::<f32>() / samples.len() as f32;
+        Ok(drain_model::recommend(
+            &model,
+            cpu_family,
+            capacity_wh,
+            target_hours,
+            typical_load,
+        ))
+    }
+    pub async fn insert_experiment(&self, report: &ExperimentReport) -> Result<i32, DbErr> {
+        experiment::insert_experiment(self.store.db.as_ref().unwrap(), report).await
+    }
+    pub async fn select_experiments(
+        &self,
+        size: u8,
+    ) -> Result<Vec<(power_experiment::Model, Vec<power_experiment_result::Model>)>, DbErr> {
+        experiment::select_experiments(self.store.db.as_ref().unwrap(), size as u64).await
+    }
+    //退出前调用，保存缓冲的数据并结束当前的历史段
+    pub async fn flush(&mut self) -> Result<(), DbErr> {
+        let now = chrono::Utc::now();
+        self.store.flush(&now.timestamp_millis()).await?;
+        self.power_store.flush(&now.timestamp_millis()).await
+    }
     pub async fn close(&mut self) {
-        self.store.mem_db.close_by_ref().await.unwrap();
         if let Some(db) = &self.store.db {
             db.close_by_ref().await.unwrap();
         }
     }
-    pub async fn get_history(&self, id: i64) -> Result<Option<HistoryInfo>, DbErr> {
+    //睡眠时长同时计入老化压力直方图
+    pub async fn insert_sleep_session(&self, session: &SleepSession) -> Result<i32, DbErr> {
+        let db = self.store.db.as_ref().unwrap();
+        stress::add_sleep_stress(db, session).await?;
+        sleep::insert_sleep_session(db, session).await
+    }
+    pub async fn select_sleep_sessions(
+        &self,
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<sleep_sessions::Model>, DbErr> {
+        sleep::select_sleep_sessions(self.store.db.as_ref().unwrap(), start, end).await
+    }
+    //当前电池的循环计数及损耗
+    pub fn cycle_health(&self, state_of_health: f32) -> Option<CycleHealth> {
+        self.store.cycles().map(|x| x.health(state_of_health))
+    }
+    pub async fn select_battery_cycles(&self) -> Result<Vec<battery_cycles::Model>, DbErr> {
+        cycles::select_battery_cycles(self.store.db.as_ref().unwrap()).await
+    }
+    //每天计算一次当前电池的综合健康度，当天已计算时返回None
+    pub async fn update_health(
+        &self,
+        battery: &battery::Status,
+    ) -> Result<Option<HealthUpdate>, DbErr> {
+        let key = battery.key();
+        let cycles = self
+            .store
+            .cycles()
+            .filter(|x| x.battery_key == key)
+            .map(|x| x.effective());
+        health::update_health(
+            self.store.db.as_ref().unwrap(),
+            &key,
+            battery.state_of_health,
+            cycles,
+            battery.timestamp,
+        )
+        .await
+    }
+    pub async fn select_health(
+        &self,
+        battery_key: &str,
+        size: u64,
+    ) -> Result<Vec<battery_health::Model>, DbErr> {
+        health::select_health(self.store.db.as_ref().unwrap(), battery_key, size).await
+    }
+    //生成当前电池的报告，先保存内存中的循环计数
+    pub async fn battery_report(
+        &mut self,
+        battery: &battery::Status,
+        now: i64,
+    ) -> Result<BatteryReport, DbErr> {
+        self.store.save_cycles().await?;
+        BatteryReport::build(self.store.db.as_ref().unwrap(), battery.into(), now).await
+    }
+    //电量/温度区间的停留时长及日历老化压力
+    pub async fn battery_stress(&self) -> Result<StressHistogram, DbErr> {
+        stress::select_stress(self.store.db.as_ref().unwrap()).await
+    }
+    //清理状态抖动产生的短历史段
+    pub async fn merge_micro_segments(&self, min_duration_ms: i64) -> Result<u64, DbErr> {
+        segments::merge_micro_segments(self.store.db.as_ref().unwrap(), min_duration_ms).await
+    }
+    pub async fn get_history(&self, id: i32) -> Result<Option<HistoryInfo>, DbErr> {
         let db = self.store.db.as_ref().unwrap();
         let row = HistoryInfo::find_by_statement(Statement::from_sql_and_values(
             sea_orm::DatabaseBackend::Sqlite,
             r#"
+SELECT * FROM (
 SELECT 
     LAG("timestamp") OVER (
 	ORDER BY "timestamp") AS prev_timestamp,
@@ -95,10 +331,8 @@ SELECT
 	*
 FROM
 	"battery_state_history"
-	WHERE "timestamp"<=$1
-ORDER BY
-	"timestamp" DESC
-LIMIT 1
+)
+WHERE "id"=$1
             "#,
             [id.into()],
         ))
@@ -0,0 +1,153 @@
+use crate::down_sample::*;
+use crate::memory_power_status;
+use crate::power_one_minutes;
+use crate::power_realtime;
+use crate::retention::{RetentionPolicy, secs_to_ms};
+use crate::sample_buffer::SampleBuffer;
+use crate::store::buffer_capacity;
+use chrono::Utc;
+use sea_orm::*;
+//功率数据与电池数据使用相同的 内存->realtime->one_minutes 降采样及保留策略，时间同为毫秒
+pub struct PowerStore {
+    pub db: DatabaseConnection,
+    pub buffer: SampleBuffer<memory_power_status::Model>,
+    last_save_at: i64,
+    interval_secs: u32,
+    pub retention: RetentionPolicy,
+}
+impl PowerStore {
+    pub fn new(db: DatabaseConnection, interval_secs: u32) -> Self {
+        Self {
+            db,
+            buffer: SampleBuffer::new(buffer_capacity(&RetentionPolicy::default())),
+            last_save_at: Utc::now().timestamp_millis(),
+            interval_secs,
+            retention: RetentionPolicy::default(),
+        }
+    }
+    pub async fn insert(
+        &mut self,
+        power: &power::Status,
+    ) -> Result<memory_power_status::Model, DbErr> {
+        let now = secs_to_ms(power.timestamp);
+        let model = self.buffer.push(memory_power_status::Model {
+            id: 0,
+            timestamp: now,
+            stapm_limit: power.stapm_limit,
+            stapm_value: power.stamp_value,
+            slow_limit: power.slow_limit,
+            slow_value: power.slow_value,
+            fast_limit: power.fast_limit,
+            fast_value: power.fast_value,
+        });
+        let interval_ms = secs_to_ms(self.interval_secs as i64);
+        if (self.last_save_at + interval_ms) < now {
+            //与电池数据相同，先合并上次保存之后缓冲的样本，再按保留时长清理
+            let start = self
+                .last_save_at
+                .max(now - secs_to_ms(self.retention.memory_secs));
+            self.last_save_at = now;
+            self.merge(start, &now).await?;
+            self.clean(&now).await?;
+        }
+        Ok(model)
+    }
+    pub async fn set_retention(
+        &mut self,
+        retention: RetentionPolicy,
+        now: &i64,
+    ) -> Result<(), DbErr> {
+        self.retention = retention;
+        self.buffer.set_capacity(buffer_capacity(&retention));
+        self.clean(now).await
+    }
+    async fn clean(&mut self, now: &i64) -> Result<(), DbErr> {
+        //clean memory samples
+        let clean_point = now - secs_to_ms(self.retention.memory_secs);
+        let removed = self.buffer.clean(clean_point);
+        println!("memory power samples delete of rows_affected({})", removed);
+        //clean power_realtime
+        let clean_point = now - secs_to_ms(self.retention.realtime_secs);
+        let res = power_realtime::Entity::delete_many()
+            .filter(power_realtime::Column::Timestamp.lt(clean_point))
+            .exec(&self.db)
+            .await?;
+        println!(
+            "power_realtime delete of rows_affected({})",
+            res.rows_affected
+        );
+        //clean power_one_minutes
+        let clean_point = now - secs_to_ms(self.retention.one_minute_secs);
+        let res = power_one_minutes::Entity::delete_many()
+            .filter(power_one_minutes::Column::Timestamp.lt(clean_point))
+            .exec(&self.db)
+            .await?;
+        println!(
+            "power_one_minutes delete of rows_affected({})",
+            res.rows_affected
+        );
+        Ok(())
+    }
+    //退出前保存内存中尚未降采样的数据
+    pub async fn flush(&mut self, now: &i64) -> Result<(), DbErr> {
+        let start = self.last_save_at;
+        self.last_save_at = *now;
+        self.merge(start, now).await
+    }
+    async fn merge(&mut self, start: i64, now: &i64) -> Result<(), DbErr> {
+        //memory samples to power_realtime，包含start所在的分组，避免上次只合并了一部分的分组被覆盖
+        let first_bucket = start - start.rem_euclid(2000);
+        let insert_rows: Vec<power_realtime::ActiveModel> = self
+            .buffer
+            .down_sample(first_bucket - 1, *now, 2000)
+            .into_iter()
+            .map(|x| x.into_active_model())
+            .collect();
+        if !insert_rows.is_empty() {
+            //退出时保存的分组可能在下次合并时再次出现
+            let res = power_realtime::Entity::insert_many(insert_rows)
+                .on_conflict(
+                    sea_query::OnConflict::column(power_realtime::Column::Timestamp)
+                        .update_columns([
+                            power_realtime::Column::StapmLimit,
+                            power_realtime::Column::StapmValue,
+                            power_realtime::Column::SlowLimit,
+                            power_realtime::Column::SlowValue,
+                            power_realtime::Column::FastLimit,
+                            power_realtime::Column::FastValue,
+                        ])
+                        .to_owned(),
+                )
+                .exec(&self.db)
+                .await?;
+            println!(
+                "memory power samples to power_realtime of last_insert_id({})",
+                res.last_insert_id
+            );
+        }
+        //power_realtime to power_one_minutes，包含start所在的分钟
+        let previous_minute = now - now.rem_euclid(60_000) - 60_000;
+        let first_minute =
+            start.min(previous_minute) - start.min(previous_minute).rem_euclid(60_000);
+        let res = self
+            .db
+            .execute(build_upsert_sql(
+                "power_one_minutes",
+                &DownSampleParams {
+                    table_name: "power_realtime".to_string(),
+                    columns: power_columns(),
+                    end_time: *now,
+                    start_time: first_minute - 1,
+                    interval: 60_000,
+                    order_field: "timestamp".to_string(),
+                    ..Default::default()
+                },
+            )?)
+            .await?;
+        println!(
+            "power_realtime to power_one_minutes of rows_affected({})",
+            res.rows_affected()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,522 @@
+use crate::{
+    battery_health,
+    cycles::{CycleCounter, load_cycle_counter},
+    health::{DAY_MS, select_health},
+    sleep::select_sleep_sessions,
+    sleep_sessions,
+};
+use chrono::DateTime;
+use sea_orm::*;
+use sea_orm_migration::MigratorTrait;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+//报告的统计范围及最近使用段的数量
+const REPORT_SPAN_MS: i64 = 7 * DAY_MS;
+const RECENT_SEGMENTS: usize = 20;
+const RECENT_SLEEP_SESSIONS: usize = 10;
+const CAPACITY_HISTORY_DAYS: u64 = 30;
+const HOUR_MS: f64 = 3_600_000.0;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    Markdown,
+    Html,
+}
+impl ReportFormat {
+    //按扩展名选择格式，.md/.markdown为Markdown，其余为HTML
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|x| x.to_str()) {
+            Some("md") | Some("markdown") => Self::Markdown,
+            _ => Self::Html,
+        }
+    }
+}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct ReportIdentity {
+    pub battery_key: String,
+    pub vendor: Option<String>,
+    pub model: Option<String>,
+    pub serial_number: Option<String>,
+    pub design_capacity: f32,
+    pub full_capacity: f32,
+    pub capacity: f32,
+    pub state_of_health: f32,
+    pub cycle_count: Option<u32>,
+}
+impl From<&battery::Status> for ReportIdentity {
+    fn from(battery: &battery::Status) -> Self {
+        let identifier = battery.identifier();
+        Self {
+            battery_key: battery.key(),
+            vendor: identifier.vendor.clone(),
+            model: identifier.model.clone(),
+            serial_number: identifier.serial_number.clone(),
+            design_capacity: battery.design_capacity,
+            full_capacity: battery.full_capacity,
+            capacity: battery.capacity,
+            state_of_health: battery.state_of_health,
+            cycle_count: battery.cycle_count,
+        }
+    }
+}
+//每天的设计容量与满充容量
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, FromQueryResult)]
+pub struct CapacityPoint {
+    pub day: i64,
+    pub design_capacity: f32,
+    pub full_capacity: f32,
+}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, FromQueryResult)]
+pub struct ReportSegment {
+    pub timestamp: i64,
+    pub end_at: Option<i64>,
+    pub state: String,
+    pub percentage: f32,
+    pub prev_percentage: Option<f32>,
+    pub capacity: f32,
+    pub prev_capacity: Option<f32>,
+    pub energy_rate: f32,
+}
+impl ReportSegment {
+    //未结束的段按报告时间计算
+    fn duration(&self, now: i64) -> i64 {
+        (self.end_at.unwrap_or(now).min(now) - self.timestamp).max(0)
+    }
+    fn energy(&self) -> Option<f32> {
+        self.prev_capacity.map(|x| self.capacity - x)
+    }
+}
+//按状态汇总的时长和电量变化
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StateSummary {
+    pub state: String,
+    pub segments: u32,
+    pub duration_ms: i64,
+    pub energy: f32,
+}
+impl StateSummary {
+    pub fn average_power(&self) -> Option<f32> {
+        match self.duration_ms > 0 {
+            true => Some(self.energy / (self.duration_ms as f64 / HOUR_MS) as f32),
+            false => None,
+        }
+    }
+}
+//按统计范围内的平均放电功率估算的续航(小时)
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct RuntimeEstimate {
+    pub discharge_power: Option<f32>,
+    pub full_hours: Option<f32>,
+    pub design_hours: Option<f32>,
+}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BatteryReport {
+    pub generated_at: i64,
+    pub span_start: i64,
+    pub identity: ReportIdentity,
+    pub cycles: CycleCounter,
+    pub health: Option<battery_health::Model>,
+    pub capacity_history: Vec<CapacityPoint>,
+    //按开始时间倒序
+    pub segments: Vec<ReportSegment>,
+    pub summaries: Vec<StateSummary>,
+    pub sleep_sessions: Vec<sleep_sessions::Model>,
+    pub runtime: RuntimeEstimate,
+}
+//报告的一节，Markdown和HTML使用相同的内容
+struct Section {
+    title: &'static str,
+    headers: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+}
+fn format_time(ms: i64) -> String {
+    DateTime::from_timestamp_millis(ms)
+        .map(|x| x.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+fn format_day(ms: i64) -> String {
+    DateTime::from_timestamp_millis(ms)
+        .map(|x| x.format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+fn format_duration(ms: i64) -> String {
+    let minutes = ms / 60_000;
+    match minutes >= 60 {
+        true => format!("{}小时{:02}分", minutes / 60, minutes % 60),
+        false => format!("{}分{:02}秒", minutes, ms / 1000 % 60),
+    }
+}
+fn format_opt<T>(value: Option<T>, f: impl Fn(T) -> String) -> String {
+    value.map(f).unwrap_or_else(|| "-".to_string())
+}
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+impl BatteryReport {
+    //统计now之前REPORT_SPAN_MS内的数据，相同的数据和now生成相同的报告
+    pub async fn build(
+        db: &DatabaseConnection,
+        identity: ReportIdentity,
+        now: i64,
+    ) -> Result<Self, DbErr> {
+        let span_start = now - REPORT_SPAN_MS;
+        let cycles = load_cycle_counter(db, &identity.battery_key).await?;
+        let health = select_health(db, &identity.battery_key, 1)
+            .await?
+            .into_iter()
+            .next();
+        let capacity_history = CapacityPoint::find_by_statement(Statement::from_sql_and_values(
+            sea_orm::DatabaseBackend::Sqlite,
+            r#"
+SELECT
+    ("timestamp" / $1) * $1 AS "day",
+    MAX("design_capacity") AS "design_capacity",
+    AVG("full_capacity") AS "full_capacity"
+FROM "battery_state_history"
+WHERE "full_capacity" > 0 AND "timestamp" <= $2
+GROUP BY "day"
+ORDER BY "day" DESC
+LIMIT $3
+"#,
+            [DAY_MS.into(), now.into(), CAPACITY_HISTORY_DAYS.into()],
+        ))
+        .all(db)
+        .await?;
+        let segments = ReportSegment::find_by_statement(Statement::from_sql_and_values(
+            sea_orm::DatabaseBackend::Sqlite,
+            r#"
+SELECT * FROM (
+SELECT
+    "timestamp", "end_at", "state", "percentage", "capacity", "energy_rate",
+    LAG("percentage") OVER (ORDER BY "timestamp") AS "prev_percentage",
+    LAG("capacity") OVER (ORDER BY "timestamp") AS "prev_capacity"
+FROM "battery_state_history"
+)
+WHERE "timestamp" BETWEEN $1 AND $2
+ORDER BY "timestamp" DESC
+"#,
+            [span_start.into(), now.into()],
+        ))
+        .all(db)
+        .await?;
+        let mut summaries: Vec<StateSummary> = Vec::new();
+        for segment in &segments {
+            let index = match summaries.iter().position(|x| x.state == segment.state) {
+                Some(index) => index,
+                None => {
+                    summaries.push(StateSummary {
+                        state: segment.state.clone(),
+                        segments: 0,
+                        duration_ms: 0,
+                        energy: 0.0,
+                    });
+                    summaries.len() - 1
+                }
+            };
+            let summary = &mut summaries[index];
+            summary.segments += 1;
+            summary.duration_ms += segment.duration(now);
+            summary.energy += segment.energy().unwrap_or(0.0);
+        }
+        summaries.sort_by(|a, b| a.state.cmp(&b.state));
+        let discharge_power = summaries
+            .iter()
+            .find(|x| x.state == "discharging")
+            .and_then(|x| x.average_power())
+            .map(|x| -x)
+            .filter(|x| *x > 0.0);
+        let runtime = RuntimeEstimate {
+            discharge_power,
+            full_hours: discharge_power.map(|x| identity.full_capacity / x),
+            design_hours: discharge_power.map(|x| identity.design_capacity / x),
+        };
+        let mut sleep_sessions = select_sleep_sessions(db, span_start, now).await?;
+        sleep_sessions.truncate(RECENT_SLEEP_SESSIONS);
+        Ok(Self {
+            generated_at: now,
+            span_start,
+            identity,
+            cycles,
+            health,
+            capacity_history,
+            segments: segments.into_iter().take(RECENT_SEGMENTS).collect(),
+            summaries,
+            sleep_sessions,
+            runtime,
+        })
+    }
+    fn sections(&self) -> Vec<Section> {
+        let identity = &self.identity;
+        let text = |x: &Option<String>| x.clone().unwrap_or_else(|| "-".to_string());
+        let mut info = vec![
+            vec!["供应商".to_string(), text(&identity.vendor)],
+            vec!["型号".to_string(), text(&identity.model)],
+            vec!["序列号".to_string(), text(&identity.serial_number)],
+            vec![
+                "设计容量".to_string(),
+                format!("{:.2}Wh", identity.design_capacity),
+            ],
+            vec![
+                "满充容量".to_string(),
+                format!("{:.2}Wh", identity.full_capacity),
+            ],
+            vec![
+                "当前容量".to_string(),
+                format!("{:.2}Wh", identity.capacity),
+            ],
+            vec![
+                "健康度".to_string(),
+                format!("{:.1}%", identity.state_of_health * 100.0),
+            ],
+        ];
+        if let Some(health) = &self.health {
+            info.push(vec![
+                "综合健康度".to_string(),
+                format!("{:.0} ({})", health.score, format_day(health.day)),
+            ]);
+        }
+        let cycles = vec![
+            vec![
+                "驱动报告".to_string(),
+                format_opt(identity.cycle_count, |x| x.to_string()),
+            ],
+            vec![
+                "按放电量折算".to_string(),
+                format!("{:.2}", self.cycles.cycles),
+            ],
+            vec![
+                "累计放电量".to_string(),
+                format!("{:.1}Wh", self.cycles.discharged_wh),
+            ],
+        ];
+        let capacity_history = self
+            .capacity_history
+            .iter()
+            .map(|x| {
+                vec![
+                    format_day(x.day),
+                    format!("{:.2}Wh", x.design_capacity),
+                    format!("{:.2}Wh", x.full_capacity),
+                    match x.design_capacity > 0.0 {
+                        true => format!("{:.1}%", x.full_capacity / x.design_capacity * 100.0),
+                        false => "-".to_string(),
+                    },
+                ]
+            })
+            .collect();
+        let segments = self
+            .segments
+            .iter()
+            .map(|x| {
+                vec![
+                    format_time(x.timestamp),
+                    x.state.clone(),
+                    format_duration(x.duration(self.generated_at)),
+                    format!(
+                        "{}-{:.0}%",
+                        format_opt(x.prev_percentage, |v| format!("{:.0}", v * 100.0)),
+                        x.percentage * 100.0
+                    ),
+                    format_opt(x.energy(), |v| format!("{:+.2}Wh", v)),
+                    format!("{:.2}W", x.energy_rate),
+                ]
+            })
+            .collect();
+        let summaries = self
+            .summaries
+            .iter()
+            .map(|x| {
+                vec![
+                    x.state.clone(),
+                    x.segments.to_string(),
+                    format_duration(x.duration_ms),
+                    format!("{:+.2}Wh", x.energy),
+                    format_opt(x.average_power(), |v| format!("{:+.2}W", v)),
+                ]
+            })
+            .collect();
+        let sleep = self
+            .sleep_sessions
+            .iter()
+            .map(|x| {
+                vec![
+                    format_time(x.start_at),
+                    format_duration(x.end_at - x.start_at),
+                    format!(
+                        "{:.0}-{:.0}%",
+                        x.start_percentage * 100.0,
+                        x.end_percentage * 100.0
+                    ),
+                    format!("{:.2}W", x.drain_rate),
+                    format!("{:.2}%/h", x.percentage_rate * 100.0),
+                ]
+            })
+            .collect();
+        let hours =
+            |x: Option<f32>| format_opt(x, |v| format_duration((v as f64 * HOUR_MS) as i64));
+        let runtime = vec![
+            vec![
+                "平均放电功率".to_string(),
+                format_opt(self.runtime.discharge_power, |v| format!("{:.2}W", v)),
+            ],
+            vec!["满充容量续航".to_string(), hours(self.runtime.full_hours)],
+            vec!["设计容量续航".to_string(), hours(self.runtime.design_hours)],
+        ];
+        vec![
+            Section {
+                title: "电池信息",
+                headers: vec!["项目", "值"],
+                rows: info,
+            },
+            Section {
+                title: "循环次数",
+                headers: vec!["项目", "值"],
+                rows: cycles,
+            },
+            Section {
+                title: "容量历史",
+                headers: vec!["日期", "设计容量", "满充容量", "比例"],
+                rows: capacity_history,
+            },
+            Section {
+                title: "最近使用",
+                headers: vec!["开始时间", "状态", "时长", "电量", "容量变化", "平均功率"],
+                rows: segments,
+            },
+            Section {
+                title: "充放电汇总",
+                headers: vec!["状态", "段数", "时长", "容量变化", "平均功率"],
+                rows: summaries,
+            },
+            Section {
+                title: "睡眠耗电",
+                headers: vec!["开始时间", "时长", "电量", "平均功率", "每小时耗电"],
+                rows: sleep,
+            },
+            Section {
+                title: "预估续航",
+                headers: vec!["项目", "值"],
+                rows: runtime,
+            },
+        ]
+    }
+    fn subtitle(&self) -> String {
+        format!(
+            "生成时间 {} UTC，统计范围 {} 至 {}",
+            format_time(self.generated_at),
+            format_time(self.span_start),
+            format_time(self.generated_at)
+        )
+    }
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# 电池报告\n\n{}\n", self.subtitle());
+        for section in self.sections() {
+            out.push_str(&format!("\n## {}\n\n", section.title));
+            if section.rows.is_empty() {
+                out.push_str("无数据\n");
+                continue;
+            }
+            out.push_str(&format!("| {} |\n", section.headers.join(" | ")));
+            out.push_str(&format!("|{}\n", " --- |".repeat(section.headers.len())));
+            for row in section.rows {
+                out.push_str(&format!("| {} |\n", row.join(" | ")));
+            }
+        }
+        out
+    }
+    pub fn to_html(&self) -> String {
+        let mut out = String::from(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>电池报告</title>
+<style>
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: left; }
+th { background: #f0f0f0; }
+</style>
+</head>
+<body>
+<h1>电池报告</h1>
+"#,
+        );
+        out.push_str(&format!("<p>{}</p>\n", escape_html(&self.subtitle())));
+        for section in self.sections() {
+            out.push_str(&format!("<h2>{}</h2>\n", section.title));
+            if section.rows.is_empty() {
+                out.push_str("<p>无数据</p>\n");
+                continue;
+            }
+            out.push_str("<table>\n<tr>");
+            for header in section.headers {
+                out.push_str(&format!("<th>{}</th>", header));
+            }
+            out.push_str("</tr>\n");
+            for row in section.rows {
+                out.push_str("<tr>");
+                for cell in row {
+                    out.push_str(&format!("<td>{}</td>", escape_html(&cell)));
+                }
+                out.push_str("</tr>\n");
+            }
+            out.push_str("</table>\n");
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Markdown => self.to_markdown(),
+            ReportFormat::Html => self.to_html(),
+        }
+    }
+}
+//直接打开历史数据库生成报告，不做异常退出的恢复，不影响正在运行的实例
+//只读方式打开时不能执行迁移，表结构落后时由正在运行或下次启动的程序升级
+async fn check_schema(db: &DatabaseConnection) -> Result<(), String> {
+    let applied: Vec<String> = db
+        .query_all(Statement::from_string(
+            sea_orm::DatabaseBackend::Sqlite,
+            r#"SELECT "version" FROM "seaql_migrations""#,
+        ))
+        .await
+        .map_err(|e| format!("history database has no schema: {}", e))?
+        .iter()
+        .filter_map(|row| row.try_get("", "version").ok())
+        .collect();
+    let pending: Vec<String> = migration::Migrator::migrations()
+        .iter()
+        .map(|x| x.name().to_string())
+        .filter(|x| !applied.contains(x))
+        .collect();
+    match pending.is_empty() {
+        true => Ok(()),
+        false => Err(format!(
+            "history database schema is out of date (pending: {}), start the app once to upgrade it",
+            pending.join(", ")
+        )),
+    }
+}
+//以只读方式打开正在使用的数据库，不修改其中的记录及表结构
+pub async fn write_battery_report(
+    db_path: &Path,
+    identity: ReportIdentity,
+    path: &Path,
+    now: i64,
+) -> Result<(), String> {
+    let db = Database::connect(format!("sqlite://{}?mode=ro", db_path.display()))
+        .await
+        .map_err(|e| e.to_string())?;
+    check_schema(&db).await?;
+    let report = BatteryReport::build(&db, identity, now)
+        .await
+        .map_err(|e| e.to_string())?;
+    db.close().await.map_err(|e| e.to_string())?;
+    std::fs::write(path, report.render(ReportFormat::from_path(path))).map_err(|e| e.to_string())
+}
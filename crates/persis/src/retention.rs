@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+
+const MINUTE: i64 = 60;
+const HOUR: i64 = 60 * MINUTE;
+const DAY: i64 = 24 * HOUR;
+//有限保留时长的上限，也避免换算为毫秒时溢出
+const MAX_MEMORY_SECS: i64 = HOUR;
+const MAX_REALTIME_SECS: i64 = 365 * DAY;
+const MAX_TIER_SECS: i64 = 5 * 365 * DAY;
+
+//保留时长换算为毫秒，溢出时取i64::MAX
+pub fn secs_to_ms(secs: i64) -> i64 {
+    secs.saturating_mul(1000)
+}
+//各层数据的保留时长(秒)，越粗的层保留越久
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct RetentionPolicy {
+    pub memory_secs: i64,
+    pub realtime_secs: i64,
+    pub one_minute_secs: i64,
+    pub one_hour_secs: i64,
+    //None表示永久保留
+    pub one_day_secs: Option<i64>,
+}
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            memory_secs: MINUTE,
+            realtime_secs: DAY,
+            one_minute_secs: 7 * DAY,
+            one_hour_secs: 365 * DAY,
+            one_day_secs: None,
+        }
+    }
+}
+impl RetentionPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        //下一层的降采样需要读取上一层最近一段时间的数据
+        let tiers = [
+            ("memory_secs", self.memory_secs, MINUTE, MAX_MEMORY_SECS),
+            ("realtime_secs", self.realtime_secs, HOUR, MAX_REALTIME_SECS),
+            ("one_minute_secs", self.one_minute_secs, DAY, MAX_TIER_SECS),
+            ("one_hour_secs", self.one_hour_secs, 7 * DAY, MAX_TIER_SECS),
+            (
+                "one_day_secs",
+                self.one_day_secs.unwrap_or(i64::MAX),
+                30 * DAY,
+                //永久保留时不限制
+                self.one_day_secs.map_or(i64::MAX, |_| MAX_TIER_SECS),
+            ),
+        ];
+        for (field, value, min, max) in tiers {
+            if value < min {
+                return Err(format!("{} must be >= {}s", field, min));
+            }
+            if value > max {
+                return Err(format!("{} must be <= {}s", field, max));
+            }
+        }
+        for pair in tiers.windows(2) {
+            if pair[0].1 > pair[1].1 {
+                return Err(format!("{} must be <= {}", pair[0].0, pair[1].0));
+            }
+        }
+        Ok(())
+    }
+}
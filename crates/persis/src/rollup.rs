@@ -0,0 +1,61 @@
+use crate::down_sample::*;
+use sea_orm::*;
+
+const METRICS: [&str; 4] = ["percentage", "energy_rate", "voltage", "cpu_load"];
+//汇总表及其时间桶长度(毫秒)，按UTC对齐
+const ROLLUP_TABLES: [(&str, i64); 2] = [
+    ("battery_one_hour", 3_600_000),
+    ("battery_one_day", 86_400_000),
+];
+
+fn rollup_columns() -> Vec<ColumnSpec> {
+    let mut columns = vec![
+        ColumnSpec::new("state", Aggregation::Last),
+        ColumnSpec::new("timestamp", Aggregation::Count).alias("samples"),
+    ];
+    for metric in METRICS {
+        for (aggregation, suffix) in [
+            (Aggregation::Min, "min"),
+            (Aggregation::Max, "max"),
+            (Aggregation::Avg, "avg"),
+            (Aggregation::Last, "last"),
+        ] {
+            columns.push(ColumnSpec::new(metric, aggregation).alias(&format!("{metric}_{suffix}")));
+        }
+    }
+    columns
+}
+//降采样结果写入汇总表，已存在的时间桶覆盖更新
+fn rollup_statement(table_name: &str, bucket: i64, bucket_ms: i64) -> Result<Statement, DbErr> {
+    build_upsert_sql(
+        table_name,
+        &DownSampleParams {
+            table_name: "battery_one_minutes".to_string(),
+            order_field: "timestamp".to_string(),
+            columns: rollup_columns(),
+            //降采样的时间范围不含起点
+            start_time: bucket - 1,
+            end_time: bucket + bucket_ms - 1,
+            interval: bucket_ms,
+            ..Default::default()
+        },
+    )
+}
+//用battery_one_minutes重新汇总timestamps所在的小时和天，重复执行结果不变，返回影响的行数
+pub async fn update_rollups(db: &DatabaseConnection, timestamps: &[i64]) -> Result<u64, DbErr> {
+    let mut rows_affected = 0;
+    for (table_name, bucket_ms) in ROLLUP_TABLES {
+        let mut buckets: Vec<i64> = timestamps
+            .iter()
+            .map(|x| x.div_euclid(bucket_ms) * bucket_ms)
+            .collect();
+        buckets.dedup();
+        for bucket in buckets {
+            let res = db
+                .execute(rollup_statement(table_name, bucket, bucket_ms)?)
+                .await?;
+            rows_affected += res.rows_affected();
+        }
+    }
+    Ok(rows_affected)
+}
@@ -0,0 +1,172 @@
+use crate::down_sample::BatterySample;
+use crate::memory_battery_status;
+use crate::memory_power_status;
+use crate::power_realtime;
+use std::collections::{BTreeMap, VecDeque};
+
+#[derive(Default)]
+struct Bucket<'a> {
+    count: u32,
+    //按写入顺序累加，与SQLite的AVG保持一致
+    sums: [f64; 4],
+    //温度可能缺失，与AVG一样忽略NULL
+    temperature_sum: f64,
+    temperature_count: u32,
+    last: Option<&'a memory_battery_status::Model>,
+}
+//可放入SampleBuffer的样本，时间为毫秒
+pub trait Sample: Clone {
+    fn timestamp(&self) -> i64;
+    fn set_id(&mut self, id: i32);
+}
+impl Sample for memory_battery_status::Model {
+    fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+    fn set_id(&mut self, id: i32) {
+        self.id = id;
+    }
+}
+impl Sample for memory_power_status::Model {
+    fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+    fn set_id(&mut self, id: i32) {
+        self.id = id;
+    }
+}
+//内存中的原始样本，按写入顺序保存，替代原先的memory_battery_status和memory_power_status内存表
+pub struct SampleBuffer<T = memory_battery_status::Model> {
+    samples: VecDeque<T>,
+    capacity: usize,
+    next_id: i32,
+}
+impl<T: Sample> SampleBuffer<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            //不按上限预分配，随样本增长
+            samples: VecDeque::new(),
+            capacity: capacity.max(1),
+            next_id: 1,
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        while self.samples.len() > self.capacity {
+            self.samples.pop_front();
+        }
+    }
+    //写入样本并分配自增id，超出容量时丢弃最早的样本
+    pub fn push(&mut self, mut sample: T) -> T {
+        sample.set_id(self.next_id);
+        self.next_id = self.next_id.wrapping_add(1);
+        if self.samples.len() >= self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample.clone());
+        sample
+    }
+    //删除timestamp早于before的样本，返回删除的数量
+    pub fn clean(&mut self, before: i64) -> usize {
+        let len = self.samples.len();
+        self.samples.retain(|x| x.timestamp() >= before);
+        len - self.samples.len()
+    }
+}
+impl SampleBuffer<memory_battery_status::Model> {
+    //与down_sample的电池列相同：时间范围(start, end]，按interval分组，状态、循环次数及剩余时间取分组内最后写入的一条，其余取平均
+    pub fn down_sample(&self, start: i64, end: i64, interval: i64) -> Vec<BatterySample> {
+        let mut buckets: BTreeMap<i64, Bucket> = BTreeMap::new();
+        for sample in self.samples.iter() {
+            if sample.timestamp <= start || sample.timestamp > end {
+                continue;
+            }
+            let bucket = buckets
+                .entry(sample.timestamp / interval * interval)
+                .or_default();
+            bucket.count += 1;
+            for (sum, value) in bucket.sums.iter_mut().zip([
+                sample.percentage,
+                sample.energy_rate,
+                sample.voltage,
+                sample.cpu_load,
+            ]) {
+                *sum += value as f64;
+            }
+            if let Some(temperature) = sample.temperature {
+                bucket.temperature_sum += temperature as f64;
+                bucket.temperature_count += 1;
+            }
+            bucket.last = Some(sample);
+        }
+        buckets
+            .into_iter()
+            .map(|(timestamp, bucket)| {
+                let avg = bucket.sums.map(|x| (x / bucket.count as f64) as f32);
+                let temperature = match bucket.temperature_count {
+                    0 => None,
+                    n => Some((bucket.temperature_sum / n as f64) as f32),
+                };
+                let last = bucket.last.unwrap();
+                BatterySample {
+                    timestamp,
+                    state: last.state.clone(),
+                    percentage: avg[0],
+                    energy_rate: avg[1],
+                    voltage: avg[2],
+                    cpu_load: avg[3],
+                    temperature,
+                    cycle_count: last.cycle_count,
+                    time_to_empty_secs: last.time_to_empty_secs,
+                    time_to_full_secs: last.time_to_full_secs,
+                }
+            })
+            .collect()
+    }
+}
+impl SampleBuffer<memory_power_status::Model> {
+    //与power_columns相同：时间范围(start, end]，按interval分组，读数和limit均取平均
+    pub fn down_sample(&self, start: i64, end: i64, interval: i64) -> Vec<power_realtime::Model> {
+        let mut buckets: BTreeMap<i64, (u32, [f64; 6])> = BTreeMap::new();
+        for sample in self.samples.iter() {
+            if sample.timestamp <= start || sample.timestamp > end {
+                continue;
+            }
+            let (count, sums) = buckets
+                .entry(sample.timestamp / interval * interval)
+                .or_default();
+            *count += 1;
+            for (sum, value) in sums.iter_mut().zip([
+                sample.stapm_limit,
+                sample.stapm_value,
+                sample.slow_limit,
+                sample.slow_value,
+                sample.fast_limit,
+                sample.fast_value,
+            ]) {
+                *sum += value as f64;
+            }
+        }
+        buckets
+            .into_iter()
+            .map(|(timestamp, (count, sums))| {
+                let avg = sums.map(|x| (x / count as f64) as f32);
+                power_realtime::Model {
+                    timestamp,
+                    stapm_limit: avg[0],
+                    stapm_value: avg[1],
+                    slow_limit: avg[2],
+                    slow_value: avg[3],
+                    fast_limit: avg[4],
+                    fast_value: avg[5],
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,93 @@
+use crate::battery_state_history;
+use sea_orm::*;
+
+//未结束的段还没有整段的平均值，不参与加权
+fn duration(row: &battery_state_history::Model) -> i64 {
+    row.end_at.map_or(0, |end| (end - row.timestamp).max(0))
+}
+//平均值按两段的时长加权，结束时的读数取被并入的段
+fn absorb(last: &mut battery_state_history::Model, row: &battery_state_history::Model) {
+    let (a, b) = (duration(last) as f32, duration(row) as f32);
+    let avg = |x: f32, y: f32| match a + b > 0.0 {
+        true => (x * a + y * b) / (a + b),
+        false => y,
+    };
+    last.energy_rate = avg(last.energy_rate, row.energy_rate);
+    last.voltage = avg(last.voltage, row.voltage);
+    last.cpu_load = avg(last.cpu_load, row.cpu_load);
+    last.temperature = match (last.temperature, row.temperature) {
+        (Some(x), Some(y)) => Some(avg(x, y)),
+        (x, y) => y.or(x),
+    };
+    last.end_at = row.end_at;
+    last.percentage = row.percentage;
+    last.capacity = row.capacity;
+    last.full_capacity = row.full_capacity;
+    last.state_of_health = row.state_of_health;
+    last.cycle_count = row.cycle_count;
+    last.time_to_empty_secs = row.time_to_empty_secs;
+    last.time_to_full_secs = row.time_to_full_secs;
+}
+//合并短于min_duration_ms的历史段及合并后相邻的同状态段，返回删除的段数
+//短段并入前一段，前一段的结束时间及结束时的读数取自短段，后续段的prev随之修正
+pub async fn merge_micro_segments(
+    db: &DatabaseConnection,
+    min_duration_ms: i64,
+) -> Result<u64, DbErr> {
+    let rows = battery_state_history::Entity::find()
+        .order_by_asc(battery_state_history::Column::Timestamp)
+        .all(db)
+        .await?;
+    let mut kept: Vec<(battery_state_history::Model, bool)> = Vec::with_capacity(rows.len());
+    let mut removed = Vec::new();
+    for mut row in rows {
+        if let Some((last, changed)) = kept.last_mut() {
+            let short = row
+                .end_at
+                .is_some_and(|end| end - row.timestamp < min_duration_ms);
+            if short || row.state == last.state {
+                absorb(last, &row);
+                *changed = true;
+                removed.push(row.id);
+                continue;
+            }
+            if row.prev.as_ref() != Some(&last.state) {
+                row.prev = Some(last.state.clone());
+                kept.push((row, true));
+                continue;
+            }
+        }
+        kept.push((row, false));
+    }
+    if removed.is_empty() {
+        return Ok(0);
+    }
+    let txn = db.begin().await?;
+    let res = battery_state_history::Entity::delete_many()
+        .filter(battery_state_history::Column::Id.is_in(removed))
+        .exec(&txn)
+        .await?;
+    for (row, _) in kept.into_iter().filter(|(_, changed)| *changed) {
+        battery_state_history::ActiveModel {
+            id: Unchanged(row.id),
+            prev: Set(row.prev),
+            end_at: Set(row.end_at),
+            percentage: Set(row.percentage),
+            capacity: Set(row.capacity),
+            full_capacity: Set(row.full_capacity),
+            state_of_health: Set(row.state_of_health),
+            energy_rate: Set(row.energy_rate),
+            voltage: Set(row.voltage),
+            cpu_load: Set(row.cpu_load),
+            temperature: Set(row.temperature),
+            cycle_count: Set(row.cycle_count),
+            time_to_empty_secs: Set(row.time_to_empty_secs),
+            time_to_full_secs: Set(row.time_to_full_secs),
+            ..Default::default()
+        }
+        .update(&txn)
+        .await?;
+    }
+    txn.commit().await?;
+    Ok(res.rows_affected)
+}
@@ -0,0 +1,159 @@
+use crate::down_sample::*;
+use crate::retention::{RetentionPolicy, secs_to_ms};
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+
+pub const SERIES_METRICS: [&str; 4] = ["percentage", "energy_rate", "voltage", "cpu_load"];
+//相邻两点间隔超过分组间隔的倍数时视为数据缺失
+const GAP_FACTOR: i64 = 2;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SeriesPoint {
+    //values与请求的metrics一一对应
+    Value {
+        timestamp: i64,
+        values: Vec<Option<f32>>,
+    },
+    //[start, end)之间没有数据，例如程序未运行或系统睡眠
+    Gap {
+        start: i64,
+        end: i64,
+    },
+}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Series {
+    pub metrics: Vec<String>,
+    //数据来源的表
+    pub source: String,
+    pub interval_ms: i64,
+    pub points: Vec<SeriesPoint>,
+}
+struct Tier {
+    table_name: &'static str,
+    resolution_ms: i64,
+    retention_ms: i64,
+    //汇总表的列名带_avg后缀
+    rollup: bool,
+}
+fn tiers(retention: &RetentionPolicy) -> [Tier; 4] {
+    [
+        Tier {
+            table_name: "battery_realtime",
+            resolution_ms: 2000,
+            retention_ms: secs_to_ms(retention.realtime_secs),
+            rollup: false,
+        },
+        Tier {
+            table_name: "battery_one_minutes",
+            resolution_ms: 60_000,
+            retention_ms: secs_to_ms(retention.one_minute_secs),
+            rollup: false,
+        },
+        Tier {
+            table_name: "battery_one_hour",
+            resolution_ms: 3_600_000,
+            retention_ms: secs_to_ms(retention.one_hour_secs),
+            rollup: true,
+        },
+        Tier {
+            table_name: "battery_one_day",
+            resolution_ms: 86_400_000,
+            retention_ms: retention.one_day_secs.map_or(i64::MAX, secs_to_ms),
+            rollup: true,
+        },
+    ]
+}
+//在保留范围覆盖start的层中，选择精度不超过所需间隔的最粗一层，都太粗时使用最细的一层
+fn select_tier(tiers: &[Tier], start: i64, needed_ms: i64, now: i64) -> &Tier {
+    let covering: Vec<&Tier> = tiers
+        .iter()
+        .filter(|x| start >= now.saturating_sub(x.retention_ms))
+        .collect();
+    covering
+        .iter()
+        .rev()
+        .find(|x| x.resolution_ms <= needed_ms)
+        .or(covering.first())
+        .copied()
+        .unwrap_or(&tiers[tiers.len() - 1])
+}
+fn push_gap(points: &mut Vec<SeriesPoint>, start: i64, end: i64, interval_ms: i64) {
+    if end - start > GAP_FACTOR * interval_ms {
+        points.push(SeriesPoint::Gap { start, end });
+    }
+}
+//按时间范围(毫秒)自动选择数据层并降采样到不超过max_points个点
+pub async fn select_series(
+    db: &DatabaseConnection,
+    retention: &RetentionPolicy,
+    metrics: &[String],
+    start: i64,
+    end: i64,
+    max_points: u32,
+    now: i64,
+) -> Result<Series, DbErr> {
+    if metrics.is_empty() {
+        return Err(DbErr::Custom("metrics is empty".to_string()));
+    }
+    if let Some(metric) = metrics
+        .iter()
+        .find(|x| !SERIES_METRICS.contains(&x.as_str()))
+    {
+        return Err(DbErr::Custom(format!("unknown metric({})", metric)));
+    }
+    if start >= end || max_points < 2 {
+        return Err(DbErr::Custom(format!(
+            "invalid range({}, {}) or max_points({})",
+            start, end, max_points
+        )));
+    }
+    let span = end - start;
+    let needed_ms = (span + max_points as i64 - 1) / max_points as i64;
+    let tiers = tiers(retention);
+    let tier = select_tier(&tiers, start, needed_ms, now);
+    //分组间隔取层精度的整数倍
+    let resolution = tier.resolution_ms;
+    let interval_ms = (needed_ms.max(resolution) + resolution - 1) / resolution * resolution;
+    let columns = metrics
+        .iter()
+        .map(|x| {
+            let column = match tier.rollup {
+                true => format!("{}_avg", x),
+                false => x.clone(),
+            };
+            ColumnSpec::new(&column, Aggregation::Avg).alias(x)
+        })
+        .collect();
+    let rows = db
+        .query_all(build_down_sample_sql(&DownSampleParams {
+            table_name: tier.table_name.to_string(),
+            order_field: "timestamp".to_string(),
+            columns,
+            //降采样的时间范围不含起点
+            start_time: start - 1,
+            end_time: end,
+            interval: interval_ms,
+            ..Default::default()
+        })?)
+        .await?;
+    let mut points = Vec::with_capacity(rows.len() + 2);
+    let mut last = start;
+    for row in rows {
+        let timestamp: i64 = row.try_get("", "timestamp")?;
+        let mut values = Vec::with_capacity(metrics.len());
+        for metric in metrics {
+            values.push(row.try_get::<Option<f32>>("", metric)?);
+        }
+        push_gap(&mut points, last, timestamp, interval_ms);
+        points.push(SeriesPoint::Value { timestamp, values });
+        last = timestamp;
+    }
+    push_gap(&mut points, last, end.min(now), interval_ms);
+    Ok(Series {
+        metrics: metrics.to_vec(),
+        source: tier.table_name.to_string(),
+        interval_ms,
+        points,
+    })
+}
@@ -0,0 +1,121 @@
+use crate::sleep_sessions;
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+#[cfg(not(windows))]
+use std::{sync::OnceLock, time::Instant};
+
+//系统时间比单调时钟多走超过此值(毫秒)时视为发生了睡眠
+pub const SLEEP_THRESHOLD_MS: i64 = 30_000;
+const HOUR_MS: f32 = 3_600_000.0;
+
+//不含睡眠时间的单调时钟(毫秒)，windows下Instant在睡眠期间也计时，改用unbiased interrupt time
+#[cfg(windows)]
+fn awake_millis() -> i64 {
+    let mut time: u64 = 0;
+    //单位为100纳秒
+    unsafe { winapi::um::realtimeapiset::QueryUnbiasedInterruptTime(&mut time) };
+    (time / 10_000) as i64
+}
+//linux的CLOCK_MONOTONIC及macos的CLOCK_UPTIME_RAW在睡眠期间不计时
+#[cfg(not(windows))]
+fn awake_millis() -> i64 {
+    static ORIGIN: OnceLock<Instant> = OnceLock::new();
+    ORIGIN.get_or_init(Instant::now).elapsed().as_millis() as i64
+}
+//单调时钟在睡眠期间不计时，系统时间照常走，两者差值的跳变即为睡眠时长
+#[derive(Clone, Copy, Debug)]
+pub struct SleepDetector {
+    threshold_ms: i64,
+    //上次检查时的(系统时间, 单调时钟)，均为毫秒
+    last: Option<(i64, i64)>,
+}
+impl Default for SleepDetector {
+    fn default() -> Self {
+        Self::new(SLEEP_THRESHOLD_MS)
+    }
+}
+impl SleepDetector {
+    pub fn new(threshold_ms: i64) -> Self {
+        Self {
+            threshold_ms,
+            last: None,
+        }
+    }
+    //使用不含睡眠时间的单调时钟检测
+    pub fn poll(&mut self, wall: i64) -> Option<(i64, i64)> {
+        self.tick(wall, awake_millis())
+    }
+    //检测到睡眠时返回估计的(开始, 结束)系统时间
+    pub fn tick(&mut self, wall: i64, mono: i64) -> Option<(i64, i64)> {
+        let last = self.last.replace((wall, mono));
+        let (last_wall, last_mono) = last?;
+        //主循环卡顿时两个时钟同步前进，差值不变
+        let awake = mono - last_mono;
+        match wall - last_wall - awake > self.threshold_ms {
+            //醒着的时间视为发生在睡眠之前
+            true => Some((last_wall + awake, wall)),
+            false => None,
+        }
+    }
+}
+//一次睡眠前后的电池读数
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SleepSession {
+    pub start_at: i64,
+    pub end_at: i64,
+    pub start_percentage: f32,
+    pub end_percentage: f32,
+    pub start_capacity: f32,
+    pub end_capacity: f32,
+    pub full_capacity: f32,
+}
+impl SleepSession {
+    pub fn hours(&self) -> f32 {
+        (self.end_at - self.start_at) as f32 / HOUR_MS
+    }
+    //睡眠期间的平均放电功率(W)，即每小时消耗的Wh
+    pub fn drain_rate(&self) -> f32 {
+        match self.hours() > 0.0 {
+            true => (self.start_capacity - self.end_capacity) / self.hours(),
+            false => 0.0,
+        }
+    }
+    //每小时消耗的电量百分比(0-1)
+    pub fn percentage_rate(&self) -> f32 {
+        match self.hours() > 0.0 {
+            true => (self.start_percentage - self.end_percentage) / self.hours(),
+            false => 0.0,
+        }
+    }
+}
+pub async fn insert_sleep_session(
+    db: &DatabaseConnection,
+    session: &SleepSession,
+) -> Result<i32, DbErr> {
+    let model = sleep_sessions::ActiveModel {
+        start_at: Set(session.start_at),
+        end_at: Set(session.end_at),
+        start_percentage: Set(session.start_percentage),
+        end_percentage: Set(session.end_percentage),
+        start_capacity: Set(session.start_capacity),
+        end_capacity: Set(session.end_capacity),
+        full_capacity: Set(session.full_capacity),
+        drain_rate: Set(session.drain_rate()),
+        percentage_rate: Set(session.percentage_rate()),
+        ..Default::default()
+    };
+    let res = sleep_sessions::Entity::insert(model).exec(db).await?;
+    Ok(res.last_insert_id)
+}
+//开始时间在[start, end]内的睡眠，按开始时间倒序
+pub async fn select_sleep_sessions(
+    db: &DatabaseConnection,
+    start: i64,
+    end: i64,
+) -> Result<Vec<sleep_sessions::Model>, DbErr> {
+    sleep_sessions::Entity::find()
+        .filter(sleep_sessions::Column::StartAt.between(start, end))
+        .order_by_desc(sleep_sessions::Column::StartAt)
+        .all(db)
+        .await
+}
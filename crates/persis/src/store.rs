@@ -1,10 +1,16 @@
+use crate::battery_one_day;
+use crate::battery_one_hour;
 use crate::battery_one_minutes;
 use crate::battery_realtime;
 use crate::battery_state_history;
+use crate::cycles::*;
 use crate::down_sample::*;
 use crate::memory_battery_status;
-use chrono::prelude::*;
-use chrono::{DateTime, Duration, Utc};
+use crate::retention::{RetentionPolicy, secs_to_ms};
+use crate::rollup::update_rollups;
+use crate::sample_buffer::SampleBuffer;
+use crate::stress::{reset_stress, update_stress};
+use chrono::Utc;
 use migration::*;
 use sea_orm::*;
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -14,19 +20,98 @@ pub enum InsertModifyed {
 }
 pub struct BatteryStore {
     pub db: Option<DatabaseConnection>,
-    pub mem_db: DatabaseConnection,
+    pub buffer: SampleBuffer,
     last_save_at: i64,
     interval_secs: u32,
     history_need_init: bool,
+    pub retention: RetentionPolicy,
+    //当前电池的软件循环计数
+    cycles: Option<CycleCounter>,
+}
+//上次异常退出时未结束的历史段，使用下一段的开始时间或最后一个样本的时间结束
+async fn recover_history(db: &DatabaseConnection) -> Result<(), DbErr> {
+    let dangling = battery_state_history::Entity::find()
+        .filter(battery_state_history::Column::EndAt.is_null())
+        .order_by_asc(battery_state_history::Column::Timestamp)
+        .all(db)
+        .await?;
+    if dangling.is_empty() {
+        return Ok(());
+    }
+    let last_realtime = battery_realtime::Entity::find()
+        .order_by_desc(battery_realtime::Column::Timestamp)
+        .one(db)
+        .await?
+        .map(|x| x.timestamp);
+    let last_minute = battery_one_minutes::Entity::find()
+        .order_by_desc(battery_one_minutes::Column::Timestamp)
+        .one(db)
+        .await?
+        .map(|x| x.timestamp);
+    let last_sample = last_realtime.max(last_minute);
+    for history in dangling {
+        let next = battery_state_history::Entity::find()
+            .filter(battery_state_history::Column::Timestamp.gt(history.timestamp))
+            .order_by_asc(battery_state_history::Column::Timestamp)
+            .one(db)
+            .await?;
+        let end_at = match next {
+            Some(next) => next.timestamp,
+            None => last_sample
+                .unwrap_or(history.timestamp)
+                .max(history.timestamp),
+        };
+        let timestamp = history.timestamp;
+        let mut model = history.into_active_model();
+        model.end_at = Set(Some(end_at));
+        model.update(db).await?;
+        println!(
+            "recover battery_state_history of timestamp({}) end_at({})",
+            timestamp, end_at
+        );
+    }
+    Ok(())
+}
+//剩余时间为0表示系统未提供
+fn time_secs(secs: u64) -> Option<i64> {
+    match secs {
+        0 => None,
+        v => Some(v as i64),
+    }
+}
+//采样间隔的下限，内存样本数按此间隔封顶
+pub const MIN_SAMPLE_INTERVAL_MS: i64 = 100;
+//内存样本数的上限，对应MAX_MEMORY_SECS按最小采样间隔的样本数
+const MAX_BUFFER_CAPACITY: i64 = 36_000;
+pub fn buffer_capacity(retention: &RetentionPolicy) -> usize {
+    (secs_to_ms(retention.memory_secs.max(1)) / MIN_SAMPLE_INTERVAL_MS).min(MAX_BUFFER_CAPACITY)
+        as usize
+}
+fn realtime_model(sample: BatterySample) -> battery_realtime::ActiveModel {
+    battery_realtime::ActiveModel {
+        id: NotSet,
+        timestamp: Set(sample.timestamp),
+        state: Set(sample.state),
+        percentage: Set(sample.percentage),
+        energy_rate: Set(sample.energy_rate),
+        voltage: Set(sample.voltage),
+        cpu_load: Set(sample.cpu_load),
+        temperature: Set(sample.temperature),
+        cycle_count: Set(sample.cycle_count),
+        time_to_empty_secs: Set(sample.time_to_empty_secs),
+        time_to_full_secs: Set(sample.time_to_full_secs),
+    }
 }
 impl Default for BatteryStore {
     fn default() -> Self {
         Self {
-            mem_db: DatabaseConnection::Disconnected,
+            buffer: SampleBuffer::new(buffer_capacity(&RetentionPolicy::default())),
             db: None,
             last_save_at: 0,
             interval_secs: 10,
             history_need_init: false,
+            retention: RetentionPolicy::default(),
+            cycles: None,
         }
     }
 }
@@ -36,19 +121,26 @@ impl BatteryStore {
             Some(val) => val,
             None => String::from("sqlite::memory:"),
         };
-        let db = Database::connect(db_url).await?;
-        let mem_db = Database::connect(String::from("sqlite::memory:")).await?;
+        let db = Database::connect(db_url.clone()).await?;
+        if !db_url.contains(":memory:") {
+            //WAL模式下异常退出不会损坏数据库，且读写互不阻塞
+            db.execute_unprepared("PRAGMA journal_mode=WAL;").await?;
+        }
         let db_is_new = !(SchemaManager::new(&db)
             .has_table("battery_realtime")
             .await?);
         migration::Migrator::up(&db, None).await?;
-        migration::Migrator::up(&mem_db, None).await?;
+        if !db_is_new {
+            recover_history(&db).await?;
+        }
         let instance = Self {
-            last_save_at: Utc::now().timestamp(),
+            last_save_at: Utc::now().timestamp_millis(),
             interval_secs,
             db: Some(db),
-            mem_db,
+            buffer: SampleBuffer::new(buffer_capacity(&RetentionPolicy::default())),
             history_need_init: db_is_new,
+            retention: RetentionPolicy::default(),
+            cycles: None,
         };
         Ok(instance)
     }
@@ -59,58 +151,85 @@ impl BatteryStore {
         f: F,
     ) -> Result<(Vec<InsertModifyed>, Option<memory_battery_status::Model>), DbErr>
     where
-        F: AsyncFnOnce(i64) -> (),
+        F: AsyncFnOnce(i32) -> (),
     {
-        let status = memory_battery_status::ActiveModel {
-            timestamp: ActiveValue::Set(battery.timestamp),
-            state: ActiveValue::Set(battery.state.to_string()),
-            percentage: ActiveValue::Set(battery.percentage),
-            energy_rate: ActiveValue::Set(battery.energy_rate),
-            voltage: ActiveValue::Set(battery.voltage),
-            cpu_load: ActiveValue::Set(system.cpuload),
-            ..Default::default()
-        };
+        let model = self.buffer.push(memory_battery_status::Model {
+            id: 0,
+            timestamp: battery.timestamp,
+            state: battery.state.to_string(),
+            percentage: battery.percentage,
+            energy_rate: battery.energy_rate,
+            voltage: battery.voltage,
+            cpu_load: system.cpuload,
+            temperature: battery.temperature,
+            cycle_count: battery.cycle_count.map(|x| x as i32),
+            time_to_empty_secs: time_secs(battery.time_to_empty_secs),
+            time_to_full_secs: time_secs(battery.time_to_full_secs),
+        });
+        self.observe_cycles(battery).await?;
         let mut changed_vec: Vec<InsertModifyed> = Vec::new();
-        let mut model = status.clone().insert(&self.mem_db).await;
-        if model.is_err() {
-            let err = model.err().unwrap();
-            println!("insert to memory_battery_status error:%{}", err);
-            if err
-                .to_string()
-                .contains("no such table: memory_battery_status")
+        let now = battery.timestamp;
+        let interval_ms = secs_to_ms(self.interval_secs as i64);
+        let new_history = self.history(&now, &battery, &system).await?;
+        let mut result_history = None;
+        if (self.last_save_at + interval_ms) < now {
+            //睡眠唤醒后先合并睡眠前缓冲的样本，再按保留时长清理
+            let start = self
+                .last_save_at
+                .max(now - secs_to_ms(self.retention.memory_secs));
+            self.last_save_at = now;
+            self.merge(start, &now).await?;
+            self.clean(&now).await?;
+            self.save_cycles().await?;
+            result_history = self.update_last_history(&now, battery, system).await?;
+        }
+        if result_history.is_some() || new_history.is_some() {
+            changed_vec.push(InsertModifyed::BatteryHistory);
+        }
+        match result_history {
+            Some(h) => f(h).await,
+            None => match new_history {
+                Some(h) => f(h).await,
+                None => (),
+            },
+        }
+        Ok((changed_vec, Some(model)))
+    }
+
+    pub fn cycles(&self) -> Option<&CycleCounter> {
+        self.cycles.as_ref()
+    }
+    //换电池时先保存原电池的计数，再读取新电池的计数
+    //上次使用之后用过其他电池时，老化压力直方图重新统计
+    async fn observe_cycles(&mut self, battery: &battery::Status) -> Result<(), DbErr> {
+        let key = battery.key();
+        if self.cycles.as_ref().is_none_or(|x| x.battery_key != key) {
+            self.save_cycles().await?;
+            let db = self.db.as_ref().unwrap();
+            let counter = load_cycle_counter(db, &key).await?;
+            if battery_switched_at(db, &key)
+                .await?
+                .is_some_and(|x| x > counter.updated_at)
             {
-                //时间长了会出现这个错误，不理解为什么，故丢弃数据，重置这个数据库连接
-                self.mem_db = Database::connect(String::from("sqlite::memory:")).await?;
-                Migrator::up(&self.mem_db, None).await?;
-                model = status.insert(&self.mem_db).await;
-            } else {
-                return Err(err);
+                reset_stress(db).await?;
             }
+            self.cycles = Some(counter);
         }
-        match model {
-            Ok(model) => {
-                let now = battery.timestamp;
-                let new_history = self.history(&now, &battery, &system).await?;
-                let mut result_history = None;
-                if (self.last_save_at + self.interval_secs as i64) < now {
-                    self.last_save_at = now;
-                    self.clean(&now).await?;
-                    self.merge(&now).await?;
-                    result_history = self.update_last_history(&now, battery, system).await?;
-                }
-                if result_history.is_some() || new_history.is_some() {
-                    changed_vec.push(InsertModifyed::BatteryHistory);
-                }
-                match result_history {
-                    Some(h) => f(h).await,
-                    None => match new_history {
-                        Some(h) => f(h).await,
-                        None => (),
-                    },
-                }
-                Ok((changed_vec, Some(model)))
-            }
-            Err(e) => Err(e),
+        if let Some(counter) = self.cycles.as_mut() {
+            counter.observe(
+                battery.raw_state,
+                battery.capacity,
+                battery.full_capacity,
+                battery.cycle_count.map(|x| x as i32),
+                battery.timestamp,
+            );
+        }
+        Ok(())
+    }
+    pub async fn save_cycles(&mut self) -> Result<(), DbErr> {
+        match self.cycles.as_mut() {
+            Some(counter) => save_cycle_counter(self.db.as_ref().unwrap(), counter).await,
+            None => Ok(()),
         }
     }
 
@@ -119,7 +238,7 @@ impl BatteryStore {
         now: &i64,
         battery: &battery::Status,
         system: &system::Status,
-    ) -> Result<Option<i64>, DbErr> {
+    ) -> Result<Option<i32>, DbErr> {
         let db = &self.db.as_ref().unwrap().clone();
         let mut last_insert_id = None;
         if !battery.state_changed {
@@ -132,6 +251,7 @@ impl BatteryStore {
                 {
                     let res =
                         battery_state_history::Entity::insert(battery_state_history::ActiveModel {
+                            id: NotSet,
                             timestamp: Set(*now),
                             state: Set(battery.state.to_string()),
                             capacity: Set(battery.capacity),
@@ -142,6 +262,10 @@ impl BatteryStore {
                             energy_rate: Set(battery.energy_rate),
                             voltage: Set(battery.voltage),
                             cpu_load: Set(system.cpuload),
+                            temperature: Set(battery.temperature),
+                            cycle_count: Set(battery.cycle_count.map(|x| x as i32)),
+                            time_to_empty_secs: Set(time_secs(battery.time_to_empty_secs)),
+                            time_to_full_secs: Set(time_secs(battery.time_to_full_secs)),
                             prev: Set(None),
                             end_at: Set(None),
                         })
@@ -159,6 +283,7 @@ impl BatteryStore {
             .one(db)
             .await?;
         let model = battery_state_history::ActiveModel {
+            id: NotSet,
             timestamp: Set(*now),
             state: Set(battery.state.to_string()),
             prev: Set(prev.as_ref().map(|v| v.state.clone())),
@@ -171,6 +296,10 @@ impl BatteryStore {
             energy_rate: Set(battery.energy_rate),
             voltage: Set(battery.voltage),
             cpu_load: Set(system.cpuload),
+            temperature: Set(battery.temperature),
+            cycle_count: Set(battery.cycle_count.map(|x| x as i32)),
+            time_to_empty_secs: Set(time_secs(battery.time_to_empty_secs)),
+            time_to_full_secs: Set(time_secs(battery.time_to_full_secs)),
         };
         if let Some(prev) = prev {
             let mut prev_model = prev.clone().into_active_model();
@@ -179,29 +308,18 @@ impl BatteryStore {
             prev_model.state_of_health = Set(battery.state_of_health);
             prev_model.save(db).await?;
         }
-        let old = battery_state_history::Entity::find_by_id(*now)
-            .one(db)
+        let res = battery_state_history::Entity::insert(model)
+            .exec(db)
             .await?;
-        if old.is_none() {
-            let res = battery_state_history::Entity::insert(model)
-                .exec(db)
-                .await?;
-            println!("history insert of last_insert_id({})", res.last_insert_id);
-            last_insert_id = Some(res.last_insert_id);
-        } else {
-            let res = battery_state_history::Entity::update(model)
-                .exec(db)
-                .await?;
-            println!("history update of timestamp({})", res.timestamp);
-        }
-        Ok(last_insert_id)
+        println!("history insert of last_insert_id({})", res.last_insert_id);
+        Ok(Some(res.last_insert_id))
     }
     async fn update_last_history(
         &mut self,
         now: &i64,
         battery: &battery::Status,
         system: &system::Status,
-    ) -> Result<Option<i64>, DbErr> {
+    ) -> Result<Option<i32>, DbErr> {
         let db = self.db.as_ref().unwrap();
         let last = battery_state_history::Entity::find()
             .filter(battery_state_history::Column::Timestamp.lt(*now))
@@ -209,6 +327,7 @@ impl BatteryStore {
             .one(db)
             .await?;
         let new_model = battery_state_history::ActiveModel {
+            id: NotSet,
             timestamp: Set(*now),
             state: Set(battery.state.to_string()),
             capacity: Set(battery.capacity),
@@ -219,9 +338,27 @@ impl BatteryStore {
             energy_rate: Set(battery.energy_rate),
             voltage: Set(battery.voltage),
             cpu_load: Set(system.cpuload),
+            temperature: Set(battery.temperature),
+            cycle_count: Set(battery.cycle_count.map(|x| x as i32)),
+            time_to_empty_secs: Set(time_secs(battery.time_to_empty_secs)),
+            time_to_full_secs: Set(time_secs(battery.time_to_full_secs)),
             prev: Set(None),
             end_at: Set(None),
         };
+        //最后一段已在退出或启动恢复时结束，重新开始记录
+        let latest = battery_state_history::Entity::find()
+            .order_by_desc(battery_state_history::Column::Timestamp)
+            .one(db)
+            .await?;
+        if let Some(latest) = latest.filter(|x| x.end_at.is_some() && x.timestamp < *now) {
+            let res = battery_state_history::Entity::insert(battery_state_history::ActiveModel {
+                prev: Set(Some(latest.state.clone())),
+                ..new_model
+            })
+            .exec(db)
+            .await?;
+            return Ok(Some(res.last_insert_id));
+        }
         let new_id = match &last {
             None => {
                 let res =
@@ -281,7 +418,8 @@ impl BatteryStore {
         let model = history;
         let db = self.db.as_ref().unwrap();
         if model.end_at.is_none() {
-            let min_start_at = *now - Duration::days(7).num_seconds();
+            //battery_one_minutes只保留one_minute_secs
+            let min_start_at = *now - secs_to_ms(self.retention.one_minute_secs);
             let timestamp = match model.timestamp < min_start_at {
                 true => min_start_at,
                 false => model.timestamp,
@@ -291,7 +429,8 @@ impl BatteryStore {
 SELECT 
     AVG(energy_rate) as energy_rate,
     AVG(voltage) as voltage,
-    AVG(cpu_load) as cpu_load
+    AVG(cpu_load) as cpu_load,
+    AVG(temperature) as temperature
 FROM battery_one_minutes
 WHERE timestamp BETWEEN {timestamp} AND {now}
 "#
@@ -319,6 +458,12 @@ WHERE timestamp BETWEEN {timestamp} AND {now}
                         model.cpu_load = Set(cpu_load);
                     })?;
                 }
+                //温度取整段的平均值，无数据时使用当前读数
+                let temperature: Option<f32> = rows.try_get("", "temperature")?;
+                model.temperature = Set(temperature.or(battery.temperature));
+                model.cycle_count = Set(battery.cycle_count.map(|x| x as i32));
+                model.time_to_empty_secs = Set(time_secs(battery.time_to_empty_secs));
+                model.time_to_full_secs = Set(time_secs(battery.time_to_full_secs));
                 model.percentage = Set(battery.percentage);
                 model.state_of_health = Set(battery.state_of_health);
                 model.capacity = Set(battery.capacity);
@@ -336,20 +481,24 @@ WHERE timestamp BETWEEN {timestamp} AND {now}
         }
         Ok(())
     }
+    //修改保留策略后立即按新策略清理
+    pub async fn set_retention(
+        &mut self,
+        retention: RetentionPolicy,
+        now: &i64,
+    ) -> Result<(), DbErr> {
+        self.retention = retention;
+        self.buffer.set_capacity(buffer_capacity(&retention));
+        self.clean(now).await
+    }
     async fn clean(&mut self, now: &i64) -> Result<(), DbErr> {
         let db = self.db.as_ref().unwrap();
-        //clean memory_battery_status
-        let clean_point = now - Duration::minutes(1).num_seconds();
-        let res = memory_battery_status::Entity::delete_many()
-            .filter(memory_battery_status::Column::Timestamp.lt(clean_point))
-            .exec(&self.mem_db)
-            .await?;
-        println!(
-            "memory_battery_status delete of rows_affected({})",
-            res.rows_affected
-        );
+        //clean memory samples
+        let clean_point = now - secs_to_ms(self.retention.memory_secs);
+        let removed = self.buffer.clean(clean_point);
+        println!("memory samples delete of rows_affected({})", removed);
         //clean battery_realtime
-        let clean_point = now - Duration::days(1).num_seconds();
+        let clean_point = now - secs_to_ms(self.retention.realtime_secs);
         let res = battery_realtime::Entity::delete_many()
             .filter(battery_realtime::Column::Timestamp.lt(clean_point))
             .exec(db)
@@ -359,7 +508,7 @@ WHERE timestamp BETWEEN {timestamp} AND {now}
             res.rows_affected
         );
         //clean battery_one_minutes
-        let clean_point = now - Duration::days(7).num_seconds();
+        let clean_point = now - secs_to_ms(self.retention.one_minute_secs);
         let res = battery_one_minutes::Entity::delete_many()
             .filter(battery_one_minutes::Column::Timestamp.lt(clean_point))
             .exec(db)
@@ -368,91 +517,112 @@ WHERE timestamp BETWEEN {timestamp} AND {now}
             "battery_one_minutes delete of rows_affected({})",
             res.rows_affected
         );
+        //clean battery_one_hour
+        let clean_point = now - secs_to_ms(self.retention.one_hour_secs);
+        let res = battery_one_hour::Entity::delete_many()
+            .filter(battery_one_hour::Column::Timestamp.lt(clean_point))
+            .exec(db)
+            .await?;
+        println!(
+            "battery_one_hour delete of rows_affected({})",
+            res.rows_affected
+        );
+        //clean battery_one_day
+        if let Some(one_day_secs) = self.retention.one_day_secs {
+            let clean_point = now - secs_to_ms(one_day_secs);
+            let res = battery_one_day::Entity::delete_many()
+                .filter(battery_one_day::Column::Timestamp.lt(clean_point))
+                .exec(db)
+                .await?;
+            println!(
+                "battery_one_day delete of rows_affected({})",
+                res.rows_affected
+            );
+        }
         Ok(())
     }
-    async fn merge(&mut self, now: &i64) -> Result<(), DbErr> {
+    //退出前保存缓冲区中尚未降采样的数据，并结束当前的历史段
+    pub async fn flush(&mut self, now: &i64) -> Result<(), DbErr> {
+        let start = self.last_save_at;
+        self.last_save_at = *now;
+        self.merge(start, now).await?;
+        self.save_cycles().await?;
         let db = self.db.as_ref().unwrap();
-        //memory_battery_status to battery_realtime
-        let insert_rows: Vec<battery_realtime::ActiveModel> = down_sample(
-            &self.mem_db,
-            &DownSampleParams {
-                table_name: "memory_battery_status".to_string(),
-                end_time: *now,
-                start_time: now - self.interval_secs as i64,
-                interval_secs: 2,
-                order_field: "id".to_string(),
-                ..Default::default()
-            },
-        )
-        .await?
-        .iter()
-        .map(|x| {
-            battery_realtime::Model {
-                timestamp: x.timestamp,
-                percentage: x.percentage,
-                state: x.state.clone(),
-                energy_rate: x.energy_rate,
-                voltage: x.voltage,
-                cpu_load: x.cpu_load,
-            }
-            .into_active_model()
-        })
-        .collect();
-        let res = battery_realtime::Entity::insert_many(insert_rows)
+        let res = battery_state_history::Entity::update_many()
+            .col_expr(battery_state_history::Column::EndAt, Expr::value(*now))
+            .filter(battery_state_history::Column::EndAt.is_null())
             .exec(db)
             .await?;
         println!(
-            "memory_battery_status to battery_realtime of last_insert_id({})",
-            res.last_insert_id
+            "battery_state_history close of rows_affected({})",
+            res.rows_affected
         );
-        //battery_realtime to battery_one_minutes
-        let now_instance = DateTime::<Utc>::from_timestamp(*now, 0).unwrap();
-        let current_minute = now_instance.trunc_subsecs(0).with_second(0).unwrap();
-        let previous_minute = current_minute - Duration::minutes(1);
-        let update_rows: Vec<battery_one_minutes::ActiveModel> = down_sample(
-            db,
-            &DownSampleParams {
-                time_formater: "%Y-%m-%dT%H:%M:00Z".to_string(),
-                table_name: "battery_realtime".to_string(),
-                end_time: *now,
-                start_time: previous_minute.timestamp(),
-                interval_secs: 1,
-                order_field: "timestamp".to_string(),
-                ..Default::default()
-            },
-        )
-        .await?
-        .iter()
-        .map(|x| {
-            battery_one_minutes::Model {
-                timestamp: x.timestamp,
-                percentage: x.percentage,
-                state: x.state.clone(),
-                energy_rate: x.energy_rate,
-                voltage: x.voltage,
-                cpu_load: x.cpu_load,
-            }
-            .into_active_model()
-        })
-        .collect();
-        for mut row in update_rows {
-            let old = battery_one_minutes::Entity::find_by_id(row.timestamp.clone().unwrap())
-                .one(db)
+        Ok(())
+    }
+    async fn merge(&mut self, start: i64, now: &i64) -> Result<(), DbErr> {
+        let db = self.db.as_ref().unwrap();
+        //memory samples to battery_realtime
+        let insert_rows: Vec<battery_realtime::ActiveModel> = self
+            .buffer
+            .down_sample(start, *now, 2000)
+            .into_iter()
+            .map(realtime_model)
+            .collect();
+        if !insert_rows.is_empty() {
+            //退出时保存的分组可能在下次合并时再次出现
+            let res = battery_realtime::Entity::insert_many(insert_rows)
+                .on_conflict(
+                    sea_query::OnConflict::column(battery_realtime::Column::Timestamp)
+                        .update_columns([
+                            battery_realtime::Column::State,
+                            battery_realtime::Column::Percentage,
+                            battery_realtime::Column::EnergyRate,
+                            battery_realtime::Column::Voltage,
+                            battery_realtime::Column::CpuLoad,
+                            battery_realtime::Column::Temperature,
+                            battery_realtime::Column::CycleCount,
+                            battery_realtime::Column::TimeToEmptySecs,
+                            battery_realtime::Column::TimeToFullSecs,
+                        ])
+                        .to_owned(),
+                )
+                .exec(db)
                 .await?;
-            if old.is_none() {
-                let res = battery_one_minutes::Entity::insert(row).exec(db).await?;
-                println!(
-                    "battery_realtime to battery_one_minutes insert of timestamp({})",
-                    res.last_insert_id
-                );
-            } else {
-                let res = battery_one_minutes::Entity::update(row).exec(db).await?;
-                println!(
-                    "battery_realtime to battery_one_minutes update of timestamp({})",
-                    res.timestamp
-                );
-            }
+            println!(
+                "memory samples to battery_realtime of last_insert_id({})",
+                res.last_insert_id
+            );
         }
+        //battery_realtime to battery_one_minutes，包含start所在的分钟
+        let previous_minute = now - now.rem_euclid(60_000) - 60_000;
+        let first_minute =
+            start.min(previous_minute) - start.min(previous_minute).rem_euclid(60_000);
+        let res = db
+            .execute(build_upsert_sql(
+                "battery_one_minutes",
+                &DownSampleParams {
+                    table_name: "battery_realtime".to_string(),
+                    end_time: *now,
+                    start_time: first_minute - 1,
+                    interval: 60_000,
+                    order_field: "timestamp".to_string(),
+                    ..Default::default()
+                },
+            )?)
+            .await?;
+        println!(
+            "battery_realtime to battery_one_minutes of rows_affected({})",
+            res.rows_affected()
+        );
+        //battery_one_minutes to battery_one_hour/battery_one_day
+        let res = update_rollups(db, &[first_minute, previous_minute, *now]).await?;
+        println!(
+            "battery_one_minutes to battery_one_hour/battery_one_day of rows_affected({})",
+            res
+        );
+        //已完整的分钟计入电量/温度直方图
+        let res = update_stress(db, previous_minute).await?;
+        println!("battery_one_minutes to battery_stress of rows({})", res);
         Ok(())
     }
 }
@@ -0,0 +1,234 @@
+use crate::battery_stress;
+use crate::sleep::SleepSession;
+use sea_orm::sea_query::Expr;
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+//电量区间的下限(0-1)
+pub const SOC_BANDS: [f32; 6] = [0.0, 0.2, 0.4, 0.6, 0.8, 0.9];
+//温度区间的下限(°C)，无温度读数的样本归入第TEMP_BANDS.len()个区间
+pub const TEMP_BANDS: [f32; 4] = [f32::NEG_INFINITY, 25.0, 35.0, 45.0];
+//各区间相对于50%电量、25°C的日历老化速率，温度每升高10°C约翻倍，温度未知时按1计算
+const SOC_FACTORS: [f64; 6] = [0.6, 0.7, 0.85, 1.0, 1.3, 1.8];
+const TEMP_FACTORS: [f64; 5] = [0.7, 1.4, 2.8, 5.6, 1.0];
+//每个battery_one_minutes样本代表的时长，睡眠期间没有样本，由add_sleep_stress补充
+const MINUTE_SECS: i64 = 60;
+
+pub fn soc_band(percentage: f32) -> i32 {
+    SOC_BANDS
+        .iter()
+        .rposition(|x| percentage >= *x)
+        .unwrap_or(0) as i32
+}
+pub fn temp_band(temperature: Option<f32>) -> i32 {
+    match temperature {
+        Some(t) => TEMP_BANDS.iter().rposition(|x| t >= *x).unwrap_or(0) as i32,
+        None => TEMP_BANDS.len() as i32,
+    }
+}
+fn soc_label(band: usize) -> String {
+    let low = SOC_BANDS[band] * 100.0;
+    match SOC_BANDS.get(band + 1) {
+        Some(high) => format!("{}-{}%", low, high * 100.0),
+        None => format!("{}-100%", low),
+    }
+}
+fn temp_label(band: usize) -> String {
+    match (TEMP_BANDS.get(band), TEMP_BANDS.get(band + 1)) {
+        (None, _) => "未知".to_string(),
+        (Some(low), Some(high)) if low.is_infinite() => format!("<{}°C", high),
+        (Some(low), Some(high)) => format!("{}-{}°C", low, high),
+        (Some(low), None) => format!("≥{}°C", low),
+    }
+}
+fn aging_factor(soc_band: usize, temp_band: usize) -> f64 {
+    SOC_FACTORS[soc_band] * TEMP_FACTORS[temp_band]
+}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StressBin {
+    pub soc_band: i32,
+    pub temp_band: i32,
+    pub soc_label: String,
+    pub temp_label: String,
+    pub secs: i64,
+}
+//电量与温度的组合直方图
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StressHistogram {
+    pub bins: Vec<StressBin>,
+    //按电量、温度区间分别汇总的秒数
+    pub soc_secs: Vec<i64>,
+    pub temp_secs: Vec<i64>,
+    pub total_secs: i64,
+    //按时间加权的平均日历老化速率，1为50%电量、25°C
+    pub aging_rate: f64,
+    //0-100，按对数刻度，0为始终处于老化最慢的区间，100为始终处于老化最快的区间
+    pub stress_score: f64,
+    //电量不低于90%且温度不低于35°C的时间占比
+    pub high_soc_hot_share: f64,
+    //已计入的最后一个battery_one_minutes时间戳
+    pub updated_at: i64,
+}
+impl StressHistogram {
+    pub fn from_rows(rows: &[battery_stress::Model]) -> Self {
+        let mut bins = Vec::with_capacity(rows.len());
+        let mut soc_secs = vec![0; SOC_BANDS.len()];
+        let mut temp_secs = vec![0; TEMP_BANDS.len() + 1];
+        let mut weighted = 0.0;
+        let mut updated_at = 0;
+        for row in rows {
+            let (soc, temp) = (row.soc_band as usize, row.temp_band as usize);
+            //区间定义调整后旧数据可能越界
+            if soc >= soc_secs.len() || temp >= temp_secs.len() {
+                continue;
+            }
+            soc_secs[soc] += row.secs;
+            temp_secs[temp] += row.secs;
+            weighted += row.secs as f64 * aging_factor(soc, temp);
+            updated_at = updated_at.max(row.updated_at);
+            bins.push(StressBin {
+                soc_band: row.soc_band,
+                temp_band: row.temp_band,
+                soc_label: soc_label(soc),
+                temp_label: temp_label(temp),
+                secs: row.secs,
+            });
+        }
+        bins.sort_by_key(|x| (x.soc_band, x.temp_band));
+        let total_secs: i64 = soc_secs.iter().sum();
+        let aging_rate = match total_secs > 0 {
+            true => weighted / total_secs as f64,
+            false => 0.0,
+        };
+        let min = SOC_FACTORS[0] * TEMP_FACTORS[0];
+        let max = SOC_FACTORS[SOC_BANDS.len() - 1] * TEMP_FACTORS[TEMP_BANDS.len() - 1];
+        let stress_score = match total_secs > 0 {
+            true => ((aging_rate / min).ln() / (max / min).ln() * 100.0).clamp(0.0, 100.0),
+            false => 0.0,
+        };
+        let mut histogram = Self {
+            bins,
+            soc_secs,
+            temp_secs,
+            total_secs,
+            aging_rate,
+            stress_score,
+            high_soc_hot_share: 0.0,
+            updated_at,
+        };
+        histogram.high_soc_hot_share = histogram.share(0.9, Some(35.0));
+        histogram
+    }
+    //电量不低于min_soc且温度不低于min_temp的时间占比，min_temp为None时不限温度
+    pub fn share(&self, min_soc: f32, min_temp: Option<f32>) -> f64 {
+        if self.total_secs == 0 {
+            return 0.0;
+        }
+        let secs: i64 = self
+            .bins
+            .iter()
+            .filter(|x| SOC_BANDS[x.soc_band as usize] >= min_soc)
+            .filter(|x| match min_temp {
+                Some(t) => TEMP_BANDS
+                    .get(x.temp_band as usize)
+                    .is_some_and(|low| *low >= t),
+                None => true,
+            })
+            .map(|x| x.secs)
+            .sum();
+        secs as f64 / self.total_secs as f64
+    }
+}
+#[derive(Debug, FromQueryResult)]
+struct MinuteSample {
+    percentage: f32,
+    temperature: Option<f32>,
+    timestamp: i64,
+}
+//把上次统计之后、until之前(含)的battery_one_minutes计入直方图，返回计入的样本数
+pub async fn update_stress(db: &DatabaseConnection, until: i64) -> Result<u64, DbErr> {
+    let watermark = battery_stress::Entity::find()
+        .order_by_desc(battery_stress::Column::UpdatedAt)
+        .one(db)
+        .await?
+        .map_or(i64::MIN, |x| x.updated_at);
+    let rows = MinuteSample::find_by_statement(Statement::from_sql_and_values(
+        sea_orm::DatabaseBackend::Sqlite,
+        r#"
+SELECT "timestamp", "percentage", "temperature" FROM "battery_one_minutes"
+WHERE "timestamp" > $1 AND "timestamp" <= $2
+ORDER BY "timestamp"
+"#,
+        [watermark.into(), until.into()],
+    ))
+    .all(db)
+    .await?;
+    let Some(last) = rows.last().map(|x| x.timestamp) else {
+        return Ok(0);
+    };
+    let mut bins: BTreeMap<(i32, i32), i64> = BTreeMap::new();
+    for row in &rows {
+        *bins
+            .entry((soc_band(row.percentage), temp_band(row.temperature)))
+            .or_default() += MINUTE_SECS;
+    }
+    let txn = db.begin().await?;
+    for ((soc, temp), secs) in bins {
+        txn.execute(Statement::from_sql_and_values(
+            sea_orm::DatabaseBackend::Sqlite,
+            r#"
+INSERT INTO "battery_stress" ("soc_band", "temp_band", "secs", "updated_at")
+VALUES ($1, $2, $3, $4)
+ON CONFLICT("soc_band", "temp_band") DO UPDATE SET
+    "secs" = "secs" + excluded."secs",
+    "updated_at" = excluded."updated_at"
+"#,
+            [soc.into(), temp.into(), secs.into(), last.into()],
+        ))
+        .await?;
+    }
+    txn.commit().await?;
+    Ok(rows.len() as u64)
+}
+//睡眠期间也在老化，整段按前后电量的平均值计入，温度未知
+//updated_at沿用已有的最大值，不影响update_stress的统计进度
+pub async fn add_sleep_stress(
+    db: &DatabaseConnection,
+    session: &SleepSession,
+) -> Result<(), DbErr> {
+    let secs = (session.end_at - session.start_at) / 1000;
+    if secs <= 0 {
+        return Ok(());
+    }
+    let percentage = (session.start_percentage + session.end_percentage) / 2.0;
+    db.execute(Statement::from_sql_and_values(
+        sea_orm::DatabaseBackend::Sqlite,
+        r#"
+INSERT INTO "battery_stress" ("soc_band", "temp_band", "secs", "updated_at")
+VALUES ($1, $2, $3, COALESCE((SELECT MAX("updated_at") FROM "battery_stress"), $4))
+ON CONFLICT("soc_band", "temp_band") DO UPDATE SET
+    "secs" = "secs" + excluded."secs"
+"#,
+        [
+            soc_band(percentage).into(),
+            temp_band(None).into(),
+            secs.into(),
+            i64::MIN.into(),
+        ],
+    ))
+    .await?;
+    Ok(())
+}
+//换电池后重新统计，保留updated_at以免再次计入换电池前的分钟样本
+pub async fn reset_stress(db: &DatabaseConnection) -> Result<(), DbErr> {
+    battery_stress::Entity::update_many()
+        .col_expr(battery_stress::Column::Secs, Expr::value(0))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+pub async fn select_stress(db: &DatabaseConnection) -> Result<StressHistogram, DbErr> {
+    let rows = battery_stress::Entity::find().all(db).await?;
+    Ok(StressHistogram::from_rows(&rows))
+}
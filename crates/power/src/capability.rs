@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+//与ryzenadj的ryzen_family枚举保持一致
+pub const FAM_RAVEN: i32 = 0;
+pub const FAM_PICASSO: i32 = 1;
+pub const FAM_RENOIR: i32 = 2;
+pub const FAM_CEZANNE: i32 = 3;
+pub const FAM_DALI: i32 = 4;
+pub const FAM_LUCIENNE: i32 = 5;
+pub const FAM_VANGOGH: i32 = 6;
+pub const FAM_REMBRANDT: i32 = 7;
+pub const FAM_MENDOCINO: i32 = 8;
+pub const FAM_PHOENIX: i32 = 9;
+pub const FAM_HAWKPOINT: i32 = 10;
+pub const FAM_STRIXPOINT: i32 = 11;
+
+//不同cpu_family支持的可调项
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Capability {
+    //tctl温度墙
+    pub tctl_temp: bool,
+    //apu表面温度墙(STT)
+    pub apu_skin_temp: bool,
+    //VRM/SoC电流限制
+    pub vrm_current: bool,
+    //stapm/slow ppt时间常数
+    pub time_constant: bool,
+    //省电/性能模式
+    pub power_mode: bool,
+    //Curve Optimizer偏移
+    pub curve_optimizer: bool,
+}
+impl Capability {
+    pub fn from_family(cpu_family: i32) -> Self {
+        match cpu_family {
+            FAM_RAVEN | FAM_PICASSO | FAM_DALI => Self {
+                tctl_temp: true,
+                apu_skin_temp: false,
+                vrm_current: true,
+                time_constant: true,
+                power_mode: true,
+                curve_optimizer: false,
+            },
+            FAM_RENOIR | FAM_LUCIENNE => Self {
+                tctl_temp: true,
+                apu_skin_temp: true,
+                vrm_current: true,
+                time_constant: true,
+                power_mode: true,
+                curve_optimizer: false,
+            },
+            FAM_VANGOGH | FAM_MENDOCINO => Self {
+                tctl_temp: true,
+                apu_skin_temp: false,
+                vrm_current: true,
+                time_constant: true,
+                power_mode: true,
+                curve_optimizer: false,
+            },
+            FAM_CEZANNE | FAM_REMBRANDT | FAM_PHOENIX | FAM_HAWKPOINT | FAM_STRIXPOINT => Self {
+                tctl_temp: true,
+                apu_skin_temp: true,
+                vrm_current: true,
+                time_constant: true,
+                power_mode: true,
+                curve_optimizer: true,
+            },
+            _ => Self::default(),
+        }
+    }
+}
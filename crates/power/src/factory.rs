@@ -0,0 +1,52 @@
+use crate::power_status::{PowerLimit, Status, write_limit};
+use crate::range::LimitError;
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+//首次初始化时读取到的stapm/slow/fast，作为出厂值保存
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FactoryLimit {
+    pub cpu_family: i32,
+    pub captured_at: i64,
+    pub limit: PowerLimit,
+}
+impl FactoryLimit {
+    pub fn capture(status: &Status) -> Self {
+        Self {
+            cpu_family: status.identifier.cpu_family,
+            captured_at: Utc::now().timestamp(),
+            limit: PowerLimit {
+                stapm_limit: status.stapm_limit,
+                slow_limit: status.slow_limit,
+                fast_limit: status.fast_limit,
+                ..Default::default()
+            },
+        }
+    }
+    //读取已保存的出厂值，不存在或cpu_family不一致时以当前值重新记录
+    pub fn load_or_capture(
+        path: &Path,
+        status: &Status,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if path.exists() {
+            let saved: FactoryLimit = serde_json::from_str(&fs::read_to_string(path)?)?;
+            if saved.cpu_family == status.identifier.cpu_family {
+                return Ok(saved);
+            }
+        }
+        let factory = Self::capture(status);
+        fs::write(path, serde_json::to_string_pretty(&factory)?)?;
+        Ok(factory)
+    }
+}
+//恢复出厂值，不经过安全范围校验，部分厂商的出厂值本身就不满足stapm<=slow<=fast
+pub fn restore_factory(factory: &FactoryLimit) -> Result<(), LimitError> {
+    let adj = unsafe { libapuadj::init_ryzenadj() };
+    if adj.is_null() {
+        Err(LimitError::NotSupport)
+    } else {
+        write_limit(adj, &factory.limit);
+        Ok(())
+    }
+}
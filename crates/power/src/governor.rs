@@ -0,0 +1,165 @@
+use crate::power_status::PowerLimit;
+use crate::range::{LimitError, LimitRange, Violation};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GovernorTarget {
+    //保持tctl温度(℃)
+    Temperature { celsius: f32 },
+    //保持电池放电功率(W)
+    Drain { watts: f32 },
+}
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct GovernorConfig {
+    pub enable: bool,
+    pub target: GovernorTarget,
+    //stapm/slow的调节范围(W)
+    pub min_limit: f32,
+    pub max_limit: f32,
+    //fast固定不参与调节
+    pub fast_limit: f32,
+    //读数缺失时使用的安全功率
+    pub safe_limit: f32,
+    pub kp: f32,
+    pub ki: f32,
+    pub kd: f32,
+    //每秒最大调整量(W)
+    pub max_step: f32,
+}
+impl Default for GovernorConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            target: GovernorTarget::Temperature { celsius: 70.0 },
+            min_limit: 8.0,
+            max_limit: 25.0,
+            fast_limit: 30.0,
+            safe_limit: 10.0,
+            kp: 0.5,
+            ki: 0.05,
+            kd: 0.1,
+            max_step: 1.0,
+        }
+    }
+}
+impl GovernorConfig {
+    //调节范围的两端及安全功率都必须在cpu_family的安全范围内
+    pub fn validate(&self, range: &LimitRange) -> Result<(), LimitError> {
+        for value in [self.min_limit, self.max_limit, self.safe_limit] {
+            range.validate(&PowerLimit {
+                stapm_limit: value,
+                slow_limit: value,
+                fast_limit: self.fast_limit,
+                ..Default::default()
+            })?;
+        }
+        let mut violations = Vec::new();
+        for (lower, lower_v, upper, upper_v) in [
+            ("min_limit", self.min_limit, "max_limit", self.max_limit),
+            ("min_limit", self.min_limit, "safe_limit", self.safe_limit),
+            ("safe_limit", self.safe_limit, "max_limit", self.max_limit),
+        ] {
+            if lower_v > upper_v {
+                violations.push(Violation::Order {
+                    lower: lower.to_string(),
+                    upper: upper.to_string(),
+                });
+            }
+        }
+        if self.max_step <= 0.0 {
+            violations.push(Violation::OutOfRange {
+                field: "max_step".to_string(),
+                value: self.max_step,
+                min: 0.0,
+                max: f32::MAX,
+            });
+        }
+        if !violations.is_empty() {
+            return Err(LimitError::Invalid { violations });
+        }
+        Ok(())
+    }
+}
+//每个tick的输入，drain为放电功率(W)，充电时为0
+#[derive(Serialize, Clone, Copy, Debug, Default)]
+pub struct GovernorInput {
+    pub temperature: Option<f32>,
+    pub drain: Option<f32>,
+}
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct GovernorOutput {
+    pub limit: PowerLimit,
+    pub measured: Option<f32>,
+    //读数缺失，已回退到安全功率
+    pub fallback: bool,
+}
+//增量式PID，输出限幅及限速，因此不需要额外处理积分饱和
+pub struct Governor {
+    pub config: GovernorConfig,
+    output: f32,
+    last_tick: Option<i64>,
+    last_error: Option<f32>,
+    prev_error: Option<f32>,
+}
+impl Governor {
+    pub fn new(config: GovernorConfig) -> Self {
+        Self {
+            output: config.safe_limit,
+            config,
+            last_tick: None,
+            last_error: None,
+            prev_error: None,
+        }
+    }
+    fn limit(&self) -> PowerLimit {
+        PowerLimit {
+            stapm_limit: self.output,
+            slow_limit: self.output,
+            fast_limit: self.config.fast_limit,
+            ..Default::default()
+        }
+    }
+    pub fn tick(&mut self, now: i64, input: &GovernorInput) -> GovernorOutput {
+        let (measured, target) = match self.config.target {
+            GovernorTarget::Temperature { celsius } => (input.temperature, celsius),
+            GovernorTarget::Drain { watts } => (input.drain, watts),
+        };
+        let measured = match measured {
+            Some(v) if v.is_finite() => v,
+            _ => {
+                self.output = self.config.safe_limit;
+                self.last_tick = None;
+                self.last_error = None;
+                self.prev_error = None;
+                return GovernorOutput {
+                    limit: self.limit(),
+                    measured: None,
+                    fallback: true,
+                };
+            }
+        };
+        let dt = match self.last_tick {
+            Some(last) => (now - last).max(1) as f32,
+            None => 1.0,
+        };
+        //超出目标时误差为正，需要降低功率
+        let error = measured - target;
+        let last_error = self.last_error.unwrap_or(error);
+        let prev_error = self.prev_error.unwrap_or(last_error);
+        let delta = self.config.kp * (error - last_error)
+            + self.config.ki * error * dt
+            + self.config.kd * (error - 2.0 * last_error + prev_error) / dt;
+        let step = self.config.max_step * dt;
+        self.output = (self.output - delta.clamp(-step, step))
+            .clamp(self.config.min_limit, self.config.max_limit);
+        self.last_tick = Some(now);
+        self.prev_error = Some(last_error);
+        self.last_error = Some(error);
+        GovernorOutput {
+            limit: self.limit(),
+            measured: Some(measured),
+            fallback: false,
+        }
+    }
+}
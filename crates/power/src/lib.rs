@@ -1,2 +1,165 @@
+pub mod capability;
+pub mod factory;
+pub mod governor;
 pub mod power_status;
+pub mod preset;
+pub mod range;
+pub use capability::*;
+pub use factory::*;
+pub use governor::*;
 pub use power_status::*;
+pub use preset::*;
+pub use range::*;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_range() {
+        let range = LimitRange::from_family(FAM_STRIXPOINT).unwrap();
+        let limit = PowerLimit {
+            stapm_limit: 15.0,
+            slow_limit: 20.0,
+            fast_limit: 25.0,
+            ..Default::default()
+        };
+        assert_eq!(range.validate(&limit), Ok(()));
+        let typo = PowerLimit {
+            fast_limit: 250.0,
+            ..limit
+        };
+        assert_eq!(
+            range.validate(&typo),
+            Err(LimitError::Invalid {
+                violations: vec![Violation::OutOfRange {
+                    field: "fast_limit".to_string(),
+                    value: 250.0,
+                    min: 5.0,
+                    max: 80.0,
+                }]
+            })
+        );
+    }
+    #[test]
+    fn validate_order() {
+        let range = LimitRange::from_family(FAM_RAVEN).unwrap();
+        let limit = PowerLimit {
+            stapm_limit: 25.0,
+            slow_limit: 20.0,
+            fast_limit: 25.0,
+            coall: Some(-10),
+            ..Default::default()
+        };
+        assert_eq!(
+            range.validate(&limit),
+            Err(LimitError::Invalid {
+                violations: vec![
+                    Violation::Unsupported {
+                        field: "coall".to_string()
+                    },
+                    Violation::Order {
+                        lower: "stapm_limit".to_string(),
+                        upper: "slow_limit".to_string()
+                    }
+                ]
+            })
+        );
+        assert!(LimitRange::from_family(-1).is_none());
+        //未收录的cpu_family退回通用范围，仍可设置基本功率
+        let generic = LimitRange::for_family(-1);
+        assert_eq!(generic, LimitRange::generic(-1));
+        let basic = PowerLimit {
+            stapm_limit: 15.0,
+            slow_limit: 20.0,
+            fast_limit: 25.0,
+            ..Default::default()
+        };
+        assert_eq!(generic.validate(&basic), Ok(()));
+        let extended = PowerLimit {
+            tctl_temp: Some(80),
+            ..basic
+        };
+        assert!(generic.validate(&extended).is_err());
+    }
+    #[test]
+    fn presets_in_range() {
+        for family in FAM_RAVEN..=FAM_STRIXPOINT {
+            let range = LimitRange::from_family(family).unwrap();
+            let presets = default_presets(family);
+            assert_eq!(presets.len(), 3);
+            for preset in presets {
+                assert_eq!(range.validate(&preset.limit), Ok(()), "{}", preset.name);
+            }
+        }
+        let names: Vec<String> = default_presets(FAM_RENOIR)
+            .into_iter()
+            .map(|x| x.name)
+            .collect();
+        assert_eq!(names, vec!["Quiet 8W", "Balanced 15W", "Turbo 30W"]);
+    }
+    #[test]
+    fn governor_holds_temperature() {
+        let config = GovernorConfig {
+            enable: true,
+            target: GovernorTarget::Temperature { celsius: 70.0 },
+            min_limit: 8.0,
+            max_limit: 25.0,
+            fast_limit: 30.0,
+            safe_limit: 15.0,
+            kp: 0.5,
+            ki: 0.5,
+            kd: 0.0,
+            max_step: 1.0,
+        };
+        let range = LimitRange::from_family(FAM_RENOIR).unwrap();
+        assert_eq!(config.validate(&range), Ok(()));
+        let mut governor = Governor::new(config);
+        let hot = GovernorInput {
+            temperature: Some(90.0),
+            drain: None,
+        };
+        //过热时逐步降低，每秒不超过max_step
+        let out = governor.tick(100, &hot);
+        assert_eq!(out.limit.stapm_limit, 14.0);
+        let out = governor.tick(102, &hot);
+        assert_eq!(out.limit.stapm_limit, 12.0);
+        for now in 103..200 {
+            governor.tick(now, &hot);
+        }
+        let out = governor.tick(200, &hot);
+        assert_eq!(out.limit.stapm_limit, 8.0);
+        assert_eq!(out.limit.slow_limit, 8.0);
+        assert_eq!(out.limit.fast_limit, 30.0);
+        let cool = GovernorInput {
+            temperature: Some(50.0),
+            drain: None,
+        };
+        let out = governor.tick(201, &cool);
+        assert_eq!(out.limit.stapm_limit, 9.0);
+    }
+    #[test]
+    fn governor_fallback() {
+        let config = GovernorConfig {
+            target: GovernorTarget::Drain { watts: 6.0 },
+            ..Default::default()
+        };
+        let mut governor = Governor::new(config);
+        let out = governor.tick(
+            1,
+            &GovernorInput {
+                temperature: Some(60.0),
+                drain: Some(3.0),
+            },
+        );
+        assert!(!out.fallback);
+        assert!(out.limit.stapm_limit > config.safe_limit);
+        let out = governor.tick(2, &GovernorInput::default());
+        assert!(out.fallback);
+        assert_eq!(out.limit.stapm_limit, config.safe_limit);
+        let invalid = GovernorConfig {
+            min_limit: 20.0,
+            ..config
+        };
+        assert!(invalid.validate(&LimitRange::from_family(FAM_RENOIR).unwrap()).is_err());
+    }
+}
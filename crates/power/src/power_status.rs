@@ -1,3 +1,5 @@
+use crate::capability::Capability;
+use crate::range::{LimitError, LimitRange};
 use chrono::prelude::*;
 use libapuadj::ryzen_access;
 use serde::{Deserialize, Serialize};
@@ -17,6 +19,7 @@ impl Default for Identifier {
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Status {
     pub identifier: Identifier,
+    pub capability: Capability,
     pub timestamp: i64,
     pub table: i32,
     pub stapm_limit: f32,
@@ -25,6 +28,24 @@ pub struct Status {
     pub slow_value: f32,
     pub fast_limit: f32,
     pub fast_value: f32,
+    //tctl温度墙(℃)
+    pub tctl_temp_limit: Option<f32>,
+    pub tctl_temp_value: Option<f32>,
+    //apu表面温度墙(℃)
+    pub apu_skin_temp_limit: Option<f32>,
+    pub apu_skin_temp_value: Option<f32>,
+    //VRM电流(A)
+    pub vrm_current: Option<f32>,
+    pub vrm_current_value: Option<f32>,
+    pub vrmsoc_current: Option<f32>,
+    pub vrmsoc_current_value: Option<f32>,
+    pub vrmmax_current: Option<f32>,
+    pub vrmsocmax_current: Option<f32>,
+    //ppt时间常数(s)
+    pub stapm_time: Option<f32>,
+    pub slow_time: Option<f32>,
+    //当前应用的预设名称，由调用方维护
+    pub active_preset: Option<String>,
 }
 
 impl Default for Status {
@@ -32,6 +53,7 @@ impl Default for Status {
         Self {
             timestamp: Utc::now().timestamp(),
             identifier: Identifier::default(),
+            capability: Capability::default(),
             table: 0,
             stapm_limit: 0.0,
             stamp_value: 0.0,
@@ -39,10 +61,48 @@ impl Default for Status {
             slow_limit: 0.0,
             fast_limit: 0.0,
             fast_value: 0.0,
+            tctl_temp_limit: None,
+            tctl_temp_value: None,
+            apu_skin_temp_limit: None,
+            apu_skin_temp_value: None,
+            vrm_current: None,
+            vrm_current_value: None,
+            vrmsoc_current: None,
+            vrmsoc_current_value: None,
+            vrmmax_current: None,
+            vrmsocmax_current: None,
+            stapm_time: None,
+            slow_time: None,
+            active_preset: None,
         }
     }
 }
+//ryzenadj对不支持的项返回NaN
+fn reading(supported: bool, value: f32) -> Option<f32> {
+    if supported && value.is_finite() {
+        Some((value * 100.0).round() / 100.0)
+    } else {
+        None
+    }
+}
 impl Status {
+    //当前读取到的设置，power_mode和Curve Optimizer无法回读，为None
+    pub fn current_limit(&self) -> PowerLimit {
+        PowerLimit {
+            stapm_limit: self.stapm_limit,
+            slow_limit: self.slow_limit,
+            fast_limit: self.fast_limit,
+            tctl_temp: self.tctl_temp_limit.map(|x| x.round() as u32),
+            apu_skin_temp: self.apu_skin_temp_limit.map(|x| x.round() as u32),
+            vrm_current: self.vrm_current,
+            vrmsoc_current: self.vrmsoc_current,
+            vrmmax_current: self.vrmmax_current,
+            vrmsocmax_current: self.vrmsocmax_current,
+            stapm_time: self.stapm_time.map(|x| x.round() as u32),
+            slow_time: self.slow_time.map(|x| x.round() as u32),
+            ..Default::default()
+        }
+    }
     pub fn refresh(&mut self, adj: &ryzen_access) {
         if !adj.is_null() {
             self.timestamp = Utc::now().timestamp();
@@ -55,6 +115,40 @@ impl Status {
             self.slow_value = (unsafe { libapuadj::get_slow_value(*adj) } * 100.0).round() / 100.0;
             self.fast_limit = (unsafe { libapuadj::get_fast_limit(*adj) } * 100.0).round() / 100.0;
             self.fast_value = (unsafe { libapuadj::get_fast_value(*adj) } * 100.0).round() / 100.0;
+
+            let cap = self.capability;
+            self.tctl_temp_limit =
+                reading(cap.tctl_temp, unsafe { libapuadj::get_tctl_temp(*adj) });
+            self.tctl_temp_value = reading(cap.tctl_temp, unsafe {
+                libapuadj::get_tctl_temp_value(*adj)
+            });
+            self.apu_skin_temp_limit = reading(cap.apu_skin_temp, unsafe {
+                libapuadj::get_apu_skin_temp_limit(*adj)
+            });
+            self.apu_skin_temp_value = reading(cap.apu_skin_temp, unsafe {
+                libapuadj::get_apu_skin_temp_value(*adj)
+            });
+            self.vrm_current =
+                reading(cap.vrm_current, unsafe { libapuadj::get_vrm_current(*adj) });
+            self.vrm_current_value = reading(cap.vrm_current, unsafe {
+                libapuadj::get_vrm_current_value(*adj)
+            });
+            self.vrmsoc_current = reading(cap.vrm_current, unsafe {
+                libapuadj::get_vrmsoc_current(*adj)
+            });
+            self.vrmsoc_current_value = reading(cap.vrm_current, unsafe {
+                libapuadj::get_vrmsoc_current_value(*adj)
+            });
+            self.vrmmax_current = reading(cap.vrm_current, unsafe {
+                libapuadj::get_vrmmax_current(*adj)
+            });
+            self.vrmsocmax_current = reading(cap.vrm_current, unsafe {
+                libapuadj::get_vrmsocmax_current(*adj)
+            });
+            self.stapm_time = reading(cap.time_constant, unsafe {
+                libapuadj::get_stapm_time(*adj)
+            });
+            self.slow_time = reading(cap.time_constant, unsafe { libapuadj::get_slow_time(*adj) });
         }
     }
 }
@@ -68,6 +162,7 @@ impl BaseStatus<Status> for Status {
             info.identifier = Identifier {
                 cpu_family: unsafe { libapuadj::get_cpu_family(adj) },
             };
+            info.capability = Capability::from_family(info.identifier.cpu_family);
             info.refresh(&adj);
 
             unsafe { libapuadj::cleanup_ryzenadj(adj) };
@@ -84,11 +179,40 @@ impl Last for Status {
         }
     }
 }
-#[derive(Serialize, Deserialize, Clone, Copy)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum PowerMode {
+    PowerSaving,
+    MaxPerformance,
+}
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
 pub struct PowerLimit {
     pub stapm_limit: f32,
     pub slow_limit: f32,
     pub fast_limit: f32,
+    //以下为可选项，None表示不修改
+    #[serde(default)]
+    pub tctl_temp: Option<u32>,
+    #[serde(default)]
+    pub apu_skin_temp: Option<u32>,
+    #[serde(default)]
+    pub vrm_current: Option<f32>,
+    #[serde(default)]
+    pub vrmsoc_current: Option<f32>,
+    #[serde(default)]
+    pub vrmmax_current: Option<f32>,
+    #[serde(default)]
+    pub vrmsocmax_current: Option<f32>,
+    #[serde(default)]
+    pub stapm_time: Option<u32>,
+    #[serde(default)]
+    pub slow_time: Option<u32>,
+    #[serde(default)]
+    pub power_mode: Option<PowerMode>,
+    //Curve Optimizer全核/核显偏移，负数为降压
+    #[serde(default)]
+    pub coall: Option<i32>,
+    #[serde(default)]
+    pub cogfx: Option<i32>,
 }
 impl Default for PowerLimit {
     fn default() -> Self {
@@ -96,24 +220,88 @@ impl Default for PowerLimit {
             stapm_limit: 0.0,
             slow_limit: 0.0,
             fast_limit: 0.0,
+            tctl_temp: None,
+            apu_skin_temp: None,
+            vrm_current: None,
+            vrmsoc_current: None,
+            vrmmax_current: None,
+            vrmsocmax_current: None,
+            stapm_time: None,
+            slow_time: None,
+            power_mode: None,
+            coall: None,
+            cogfx: None,
         }
     }
 }
-pub fn set_limit(limit: &PowerLimit) -> Result<(), Box<dyn std::error::Error>> {
-    if limit.fast_limit > 0.0 && limit.slow_limit > 0.0 && limit.stapm_limit > 0.0 {
-        let adj = unsafe { libapuadj::init_ryzenadj() };
-        if adj.is_null() {
-            Err("not support".into())
-        } else {
-            unsafe {
-                libapuadj::set_stapm_limit(adj, (limit.stapm_limit * 1000.0) as u32);
-                libapuadj::set_fast_limit(adj, (limit.fast_limit * 1000.0) as u32);
-                libapuadj::set_slow_limit(adj, (limit.slow_limit * 1000.0) as u32);
-                libapuadj::cleanup_ryzenadj(adj);
+//ryzenadj使用0x100000减去绝对值表示负偏移
+fn curve_optimizer_value(offset: i32) -> u32 {
+    if offset < 0 {
+        (0x100000 + offset) as u32
+    } else {
+        offset as u32
+    }
+}
+//写入限制并释放adj，调用方负责校验
+pub(crate) fn write_limit(adj: ryzen_access, limit: &PowerLimit) {
+    unsafe {
+        libapuadj::set_stapm_limit(adj, (limit.stapm_limit * 1000.0) as u32);
+        libapuadj::set_fast_limit(adj, (limit.fast_limit * 1000.0) as u32);
+        libapuadj::set_slow_limit(adj, (limit.slow_limit * 1000.0) as u32);
+        if let Some(v) = limit.tctl_temp {
+            libapuadj::set_tctl_temp(adj, v);
+        }
+        if let Some(v) = limit.apu_skin_temp {
+            libapuadj::set_apu_skin_temp_limit(adj, v);
+        }
+        if let Some(v) = limit.vrm_current {
+            libapuadj::set_vrm_current(adj, (v * 1000.0) as u32);
+        }
+        if let Some(v) = limit.vrmsoc_current {
+            libapuadj::set_vrmsoc_current(adj, (v * 1000.0) as u32);
+        }
+        if let Some(v) = limit.vrmmax_current {
+            libapuadj::set_vrmmax_current(adj, (v * 1000.0) as u32);
+        }
+        if let Some(v) = limit.vrmsocmax_current {
+            libapuadj::set_vrmsocmax_current(adj, (v * 1000.0) as u32);
+        }
+        if let Some(v) = limit.stapm_time {
+            libapuadj::set_stapm_time(adj, v);
+        }
+        if let Some(v) = limit.slow_time {
+            libapuadj::set_slow_time(adj, v);
+        }
+        match limit.power_mode {
+            Some(PowerMode::PowerSaving) => {
+                libapuadj::set_power_saving(adj);
             }
-            Ok(())
+            Some(PowerMode::MaxPerformance) => {
+                libapuadj::set_max_performance(adj);
+            }
+            None => (),
         }
+        if let Some(v) = limit.coall {
+            libapuadj::set_coall(adj, curve_optimizer_value(v));
+        }
+        if let Some(v) = limit.cogfx {
+            libapuadj::set_cogfx(adj, curve_optimizer_value(v));
+        }
+        libapuadj::cleanup_ryzenadj(adj);
+    }
+}
+pub fn set_limit(limit: &PowerLimit) -> Result<(), LimitError> {
+    let adj = unsafe { libapuadj::init_ryzenadj() };
+    if adj.is_null() {
+        Err(LimitError::NotSupport)
     } else {
-        Err("Invalid power limit values".into())
+        let cpu_family = unsafe { libapuadj::get_cpu_family(adj) };
+        if let Err(e) = LimitRange::for_family(cpu_family).validate(limit) {
+            unsafe { libapuadj::cleanup_ryzenadj(adj) };
+            Err(e)
+        } else {
+            write_limit(adj, limit);
+            Ok(())
+        }
     }
 }
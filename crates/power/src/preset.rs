@@ -0,0 +1,69 @@
+use crate::capability::*;
+use crate::power_status::PowerLimit;
+use crate::range::LimitRange;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Preset {
+    pub name: String,
+    pub limit: PowerLimit,
+}
+//各cpu_family出厂默认的stapm/slow/fast
+pub fn stock_limit(cpu_family: i32) -> Option<PowerLimit> {
+    let (stapm_limit, slow_limit, fast_limit) = match cpu_family {
+        FAM_VANGOGH => (15.0, 15.0, 15.0),
+        FAM_DALI | FAM_MENDOCINO => (15.0, 18.0, 25.0),
+        FAM_RAVEN | FAM_PICASSO | FAM_RENOIR | FAM_LUCIENNE | FAM_CEZANNE | FAM_REMBRANDT => {
+            (15.0, 20.0, 25.0)
+        }
+        FAM_PHOENIX | FAM_HAWKPOINT => (28.0, 33.0, 40.0),
+        FAM_STRIXPOINT => (28.0, 33.0, 40.0),
+        _ => return None,
+    };
+    Some(PowerLimit {
+        stapm_limit,
+        slow_limit,
+        fast_limit,
+        ..Default::default()
+    })
+}
+//按stock的比例缩放，并限制在安全范围内
+fn scale(stock: &PowerLimit, range: &LimitRange, factor: f32) -> PowerLimit {
+    let stapm_limit = (stock.stapm_limit * factor)
+        .round()
+        .clamp(range.stapm_limit.min, range.stapm_limit.max);
+    let ratio = stapm_limit / stock.stapm_limit;
+    PowerLimit {
+        stapm_limit,
+        slow_limit: (stock.slow_limit * ratio)
+            .round()
+            .clamp(stapm_limit, range.slow_limit.max),
+        fast_limit: (stock.fast_limit * ratio)
+            .round()
+            .clamp(stapm_limit, range.fast_limit.max),
+        ..Default::default()
+    }
+}
+//以出厂比例生成指定stapm的slow/fast
+pub fn limit_for_stapm(cpu_family: i32, stapm_limit: f32) -> Option<PowerLimit> {
+    let stock = stock_limit(cpu_family)?;
+    let range = LimitRange::from_family(cpu_family)?;
+    Some(scale(&stock, &range, stapm_limit / stock.stapm_limit))
+}
+//根据cpu_family的出厂功率生成Quiet/Balanced/Turbo三个预设
+pub fn default_presets(cpu_family: i32) -> Vec<Preset> {
+    let (stock, range) = match (stock_limit(cpu_family), LimitRange::from_family(cpu_family)) {
+        (Some(stock), Some(range)) => (stock, range),
+        _ => return Vec::new(),
+    };
+    [("Quiet", 0.5), ("Balanced", 1.0), ("Turbo", 2.0)]
+        .iter()
+        .map(|(name, factor)| {
+            let limit = scale(&stock, &range, *factor);
+            Preset {
+                name: format!("{} {}W", name, limit.stapm_limit),
+                limit,
+            }
+        })
+        .collect()
+}
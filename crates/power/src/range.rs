@@ -0,0 +1,282 @@
+use crate::capability::*;
+use crate::power_status::PowerLimit;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Range {
+    pub min: f32,
+    pub max: f32,
+}
+impl Range {
+    const fn new(min: f32, max: f32) -> Self {
+        Self { min, max }
+    }
+    pub fn contains(&self, value: f32) -> bool {
+        value >= self.min && value <= self.max
+    }
+}
+//超出范围或不满足约束的具体项
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Violation {
+    Unsupported {
+        field: String,
+    },
+    OutOfRange {
+        field: String,
+        value: f32,
+        min: f32,
+        max: f32,
+    },
+    //lower必须小于等于upper
+    Order {
+        lower: String,
+        upper: String,
+    },
+}
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::Unsupported { field } => write!(f, "{} not support", field),
+            Violation::OutOfRange {
+                field,
+                value,
+                min,
+                max,
+            } => write!(f, "{}={} out of range [{}, {}]", field, value, min, max),
+            Violation::Order { lower, upper } => write!(f, "{} must be <= {}", lower, upper),
+        }
+    }
+}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LimitError {
+    //未能初始化ryzenadj
+    NotSupport,
+    Invalid { violations: Vec<Violation> },
+}
+impl std::fmt::Display for LimitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LimitError::NotSupport => write!(f, "not support"),
+            LimitError::Invalid { violations } => {
+                let text: Vec<String> = violations.iter().map(|x| x.to_string()).collect();
+                write!(f, "invalid power limit: {}", text.join("; "))
+            }
+        }
+    }
+}
+impl std::error::Error for LimitError {}
+
+//某个cpu_family允许设置的安全范围
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct LimitRange {
+    pub cpu_family: i32,
+    pub capability: Capability,
+    //功率(W)
+    pub stapm_limit: Range,
+    pub slow_limit: Range,
+    pub fast_limit: Range,
+    //温度(℃)
+    pub tctl_temp: Range,
+    pub apu_skin_temp: Range,
+    //电流(A)
+    pub vrm_current: Range,
+    //时间常数(s)
+    pub stapm_time: Range,
+    pub slow_time: Range,
+    //Curve Optimizer偏移
+    pub curve_optimizer: Range,
+}
+impl LimitRange {
+    pub fn from_family(cpu_family: i32) -> Option<Self> {
+        let (stapm, slow, fast) = match cpu_family {
+            FAM_VANGOGH => (
+                Range::new(3.0, 20.0),
+                Range::new(3.0, 25.0),
+                Range::new(3.0, 30.0),
+            ),
+            FAM_DALI | FAM_MENDOCINO => (
+                Range::new(5.0, 25.0),
+                Range::new(5.0, 30.0),
+                Range::new(5.0, 35.0),
+            ),
+            FAM_RAVEN | FAM_PICASSO | FAM_RENOIR | FAM_LUCIENNE | FAM_CEZANNE | FAM_REMBRANDT => (
+                Range::new(5.0, 54.0),
+                Range::new(5.0, 60.0),
+                Range::new(5.0, 65.0),
+            ),
+            FAM_PHOENIX | FAM_HAWKPOINT | FAM_STRIXPOINT => (
+                Range::new(5.0, 65.0),
+                Range::new(5.0, 70.0),
+                Range::new(5.0, 80.0),
+            ),
+            _ => return None,
+        };
+        Some(Self {
+            cpu_family,
+            capability: Capability::from_family(cpu_family),
+            stapm_limit: stapm,
+            slow_limit: slow,
+            fast_limit: fast,
+            tctl_temp: Range::new(50.0, 100.0),
+            apu_skin_temp: Range::new(25.0, 60.0),
+            vrm_current: Range::new(10.0, 150.0),
+            stapm_time: Range::new(1.0, 1000.0),
+            slow_time: Range::new(1.0, 30.0),
+            curve_optimizer: Range::new(-50.0, 30.0),
+        })
+    }
+    //未收录的cpu_family使用保守的通用范围，扩展参数按能力表拒绝
+    pub fn generic(cpu_family: i32) -> Self {
+        Self {
+            cpu_family,
+            capability: Capability::from_family(cpu_family),
+            stapm_limit: Range::new(3.0, 35.0),
+            slow_limit: Range::new(3.0, 40.0),
+            fast_limit: Range::new(3.0, 45.0),
+            tctl_temp: Range::new(50.0, 95.0),
+            apu_skin_temp: Range::new(25.0, 50.0),
+            vrm_current: Range::new(10.0, 100.0),
+            stapm_time: Range::new(1.0, 1000.0),
+            slow_time: Range::new(1.0, 30.0),
+            curve_optimizer: Range::new(-30.0, 0.0),
+        }
+    }
+    pub fn for_family(cpu_family: i32) -> Self {
+        Self::from_family(cpu_family).unwrap_or_else(|| Self::generic(cpu_family))
+    }
+    pub fn validate(&self, limit: &PowerLimit) -> Result<(), LimitError> {
+        let mut violations = Vec::new();
+        let cap = &self.capability;
+        let mut check = |field: &str, value: Option<f32>, range: &Range, supported: bool| {
+            if let Some(value) = value {
+                if !supported {
+                    violations.push(Violation::Unsupported {
+                        field: field.to_string(),
+                    });
+                } else if !range.contains(value) {
+                    violations.push(Violation::OutOfRange {
+                        field: field.to_string(),
+                        value,
+                        min: range.min,
+                        max: range.max,
+                    });
+                }
+            }
+        };
+        check(
+            "stapm_limit",
+            Some(limit.stapm_limit),
+            &self.stapm_limit,
+            true,
+        );
+        check("slow_limit", Some(limit.slow_limit), &self.slow_limit, true);
+        check("fast_limit", Some(limit.fast_limit), &self.fast_limit, true);
+        check(
+            "tctl_temp",
+            limit.tctl_temp.map(|x| x as f32),
+            &self.tctl_temp,
+            cap.tctl_temp,
+        );
+        check(
+            "apu_skin_temp",
+            limit.apu_skin_temp.map(|x| x as f32),
+            &self.apu_skin_temp,
+            cap.apu_skin_temp,
+        );
+        check(
+            "vrm_current",
+            limit.vrm_current,
+            &self.vrm_current,
+            cap.vrm_current,
+        );
+        check(
+            "vrmsoc_current",
+            limit.vrmsoc_current,
+            &self.vrm_current,
+            cap.vrm_current,
+        );
+        check(
+            "vrmmax_current",
+            limit.vrmmax_current,
+            &self.vrm_current,
+            cap.vrm_current,
+        );
+        check(
+            "vrmsocmax_current",
+            limit.vrmsocmax_current,
+            &self.vrm_current,
+            cap.vrm_current,
+        );
+        check(
+            "stapm_time",
+            limit.stapm_time.map(|x| x as f32),
+            &self.stapm_time,
+            cap.time_constant,
+        );
+        check(
+            "slow_time",
+            limit.slow_time.map(|x| x as f32),
+            &self.slow_time,
+            cap.time_constant,
+        );
+        check(
+            "coall",
+            limit.coall.map(|x| x as f32),
+            &self.curve_optimizer,
+            cap.curve_optimizer,
+        );
+        check(
+            "cogfx",
+            limit.cogfx.map(|x| x as f32),
+            &self.curve_optimizer,
+            cap.curve_optimizer,
+        );
+        if limit.power_mode.is_some() && !cap.power_mode {
+            violations.push(Violation::Unsupported {
+                field: "power_mode".to_string(),
+            });
+        }
+        //stapm <= slow <= fast
+        let mut order = |lower: &str, lower_v: Option<f32>, upper: &str, upper_v: Option<f32>| {
+            if let (Some(l), Some(u)) = (lower_v, upper_v)
+                && l > u
+            {
+                violations.push(Violation::Order {
+                    lower: lower.to_string(),
+                    upper: upper.to_string(),
+                });
+            }
+        };
+        order(
+            "stapm_limit",
+            Some(limit.stapm_limit),
+            "slow_limit",
+            Some(limit.slow_limit),
+        );
+        order(
+            "slow_limit",
+            Some(limit.slow_limit),
+            "fast_limit",
+            Some(limit.fast_limit),
+        );
+        order(
+            "vrm_current",
+            limit.vrm_current,
+            "vrmmax_current",
+            limit.vrmmax_current,
+        );
+        order(
+            "vrmsoc_current",
+            limit.vrmsoc_current,
+            "vrmsocmax_current",
+            limit.vrmsocmax_current,
+        );
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(LimitError::Invalid { violations })
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use std::sync::Arc;
 
 use crate::config;
+use crate::experiment;
+use crate::rules;
 use crate::session;
 use crate::windows;
 use chrono::Duration;
@@ -16,19 +18,20 @@ pub async fn get_config(
     state: State<'_, Arc<Mutex<session::SessionState>>>,
 ) -> Result<config::Config, ()> {
     let state = state.lock().await;
-    Ok(state.config)
+    Ok(state.config.clone())
 }
 
 #[command]
 pub async fn set_config(
     app_handle: tauri::AppHandle,
     state: State<'_, Arc<Mutex<session::SessionState>>>,
-    config: config::Config,
+    mut config: config::Config,
 ) -> Result<bool, ()> {
     let mut state = state.lock().await;
+    config.keep_managed(&state.config);
+    config::set_autostart(&app_handle, config.auto_start);
     state.config = config;
     config::save_config(&state.config).expect("set_config err.");
-    config::set_autostart(&app_handle, config.auto_start);
 
     Ok(true)
 }
@@ -74,30 +77,31 @@ pub async fn set_power_limit(
     app_handle: tauri::AppHandle,
     state: State<'_, Arc<Mutex<session::SessionState>>>,
     limit: power::PowerLimit,
-) -> Result<(bool, Option<power::Status>), ()> {
-    let mut state = state.lock().await;
-    if !state.is_admin || !state.system.support_power_set || state.power.is_none() {
-        return Ok((false, None));
-    }
-    let result = match power::set_limit(&limit) {
-        Ok(_) => {
-            let info = state.power.as_mut().unwrap();
-            info.last();
-            if info.stapm_limit == limit.stapm_limit
-                && info.slow_limit == limit.slow_limit
-                && info.fast_limit == limit.fast_limit
-            {
-                (true, Some(info.clone()))
-            } else {
-                (false, Some(info.clone()))
-            }
-        }
-        Err(_) => (false, None),
-    };
-    if result.0 {
+) -> Result<(bool, Option<power::Status>), power::LimitError> {
+    let mut state = state.lock().await;
+    let (applied, info) = state
+        .apply_power_limit(&limit, persis::PowerAuditSource::User, None)
+        .map_err(|e| {
+            log!(Level::Warn, "command set_power_limit rejected:{}", e);
+            e
+        })?;
+    if applied {
+        state.set_active_preset(None);
         session::EventChannel::emit_ui_update(&app_handle, &state);
+        return Ok((applied, state.power.clone()));
     }
-    Ok(result)
+    Ok((applied, info))
+}
+#[command]
+pub async fn get_power_limit_range(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+) -> Result<Option<power::LimitRange>, ()> {
+    let state = state.lock().await;
+    Ok(state
+        .power
+        .as_ref()
+        .map(|x| power::LimitRange::for_family(x.identifier.cpu_family)))
 }
 #[command]
 pub async fn get_system(
@@ -116,13 +120,244 @@ pub async fn set_power_limit_lock(
 ) -> Result<bool, ()> {
     let mut state = state.lock().await;
     if state.is_admin && state.system.support_power_set {
-        state.power_lock.enable = lock;
-        state.power_lock.limit = limit;
+        state.set_power_lock(lock, limit, None);
+        state.set_active_preset(None);
         Ok(true)
     } else {
         Ok(false)
     }
 }
+#[command]
+pub async fn restore_power_limit(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+) -> Result<(bool, Option<power::Status>), power::LimitError> {
+    let mut state = state.lock().await;
+    state.restore_power_limit()
+}
+#[command]
+pub async fn get_power_factory_limit(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+) -> Result<Option<power::FactoryLimit>, ()> {
+    let state = state.lock().await;
+    Ok(state.factory_limit.clone())
+}
+#[command]
+pub async fn get_governor(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+) -> Result<(power::GovernorConfig, Option<power::GovernorOutput>), ()> {
+    let state = state.lock().await;
+    Ok((state.config.governor, state.governor_output))
+}
+#[command]
+pub async fn set_governor(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+    governor: power::GovernorConfig,
+) -> Result<bool, power::LimitError> {
+    let mut state = state.lock().await;
+    if !state.is_admin || !state.system.support_power_set {
+        return Ok(false);
+    }
+    if governor.enable {
+        let cpu_family = match &state.power {
+            Some(info) => info.identifier.cpu_family,
+            None => return Ok(false),
+        };
+        governor.validate(&power::LimitRange::for_family(cpu_family))?;
+    }
+    state.set_governor(governor);
+    Ok(true)
+}
+#[command]
+pub async fn start_experiment(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+    plan: experiment::ExperimentPlan,
+) -> Result<bool, String> {
+    let mut state = state.lock().await;
+    if !state.is_admin || !state.system.support_power_set {
+        return Ok(false);
+    }
+    plan.validate(&state)?;
+    state.stop_experiment();
+    let restore = match experiment::ExperimentRestore::capture(&state) {
+        Some(v) => v,
+        None => return Ok(false),
+    };
+    if let Some(saved) = &restore.lock {
+        state.set_power_lock(false, saved.limit, saved.preset.clone());
+    }
+    if let Some(mut governor) = restore.governor {
+        governor.enable = false;
+        state.set_governor(governor);
+    }
+    state.experiment = Some(experiment::Experiment::new(
+        plan,
+        Utc::now().timestamp(),
+        restore,
+    ));
+    Ok(true)
+}
+#[command]
+pub async fn stop_experiment(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+) -> Result<bool, ()> {
+    let mut state = state.lock().await;
+    let running = state.experiment.is_some();
+    state.stop_experiment();
+    Ok(running)
+}
+#[command]
+pub async fn get_experiment(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+) -> Result<
+    (
+        Option<experiment::ExperimentProgress>,
+        Option<persis::ExperimentReport>,
+    ),
+    (),
+> {
+    let state = state.lock().await;
+    Ok((
+        state.experiment.as_ref().map(|x| x.progress()),
+        state.experiment_report.clone(),
+    ))
+}
+#[command]
+pub async fn get_experiments(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+    size: u8,
+) -> Result<
+    Option<
+        Vec<(
+            persis::power_experiment::Model,
+            Vec<persis::power_experiment_result::Model>,
+        )>,
+    >,
+    String,
+> {
+    let state = state.lock().await;
+    match &state.persis {
+        Some(persis) => Ok(Some(
+            persis
+                .select_experiments(size)
+                .await
+                .map_err(|e| e.to_string())?,
+        )),
+        None => Ok(None),
+    }
+}
+#[command]
+pub async fn get_power_presets(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+) -> Result<Vec<power::Preset>, ()> {
+    let state = state.lock().await;
+    Ok(state.config.power_presets.clone())
+}
+#[command]
+pub async fn save_power_preset(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+    preset: power::Preset,
+) -> Result<bool, power::LimitError> {
+    let mut state = state.lock().await;
+    if let Some(range) = state
+        .power
+        .as_ref()
+        .map(|x| power::LimitRange::for_family(x.identifier.cpu_family))
+    {
+        range.validate(&preset.limit)?;
+    }
+    let presets = &mut state.config.power_presets;
+    match presets.iter_mut().find(|x| x.name == preset.name) {
+        Some(old) => *old = preset,
+        None => presets.push(preset),
+    }
+    config::save_config(&state.config).expect("save_power_preset err.");
+    Ok(true)
+}
+#[command]
+pub async fn delete_power_preset(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+    name: String,
+) -> Result<bool, ()> {
+    let mut state = state.lock().await;
+    let len = state.config.power_presets.len();
+    state.config.power_presets.retain(|x| x.name != name);
+    if state.config.power_presets.len() == len {
+        return Ok(false);
+    }
+    config::save_config(&state.config).expect("delete_power_preset err.");
+    Ok(true)
+}
+#[command]
+pub async fn apply_power_preset(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+    name: String,
+) -> Result<(bool, Option<power::Status>), power::LimitError> {
+    let mut state = state.lock().await;
+    let preset = match state.find_power_preset(&name) {
+        Some(v) => v,
+        None => return Ok((false, None)),
+    };
+    let (applied, _) = state
+        .apply_power_limit(
+            &preset.limit,
+            persis::PowerAuditSource::User,
+            Some(name.clone()),
+        )
+        .map_err(|e| {
+            log!(
+                Level::Warn,
+                "command apply_power_preset({}) rejected:{}",
+                name,
+                e
+            );
+            e
+        })?;
+    if applied {
+        state.set_active_preset(Some(name));
+        session::EventChannel::emit_ui_update(&app_handle, &state);
+    }
+    Ok((applied, state.power.clone()))
+}
+#[command]
+pub async fn lock_power_preset(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+    name: String,
+    lock: bool,
+) -> Result<bool, power::LimitError> {
+    let mut state = state.lock().await;
+    if !state.is_admin || !state.system.support_power_set {
+        return Ok(false);
+    }
+    let preset = match state.find_power_preset(&name) {
+        Some(v) => v,
+        None => return Ok(false),
+    };
+    if let Some(range) = state
+        .power
+        .as_ref()
+        .map(|x| power::LimitRange::for_family(x.identifier.cpu_family))
+    {
+        range.validate(&preset.limit)?;
+    }
+    state.set_power_lock(lock, preset.limit, Some(name));
+    if !lock {
+        state.set_active_preset(None);
+    }
+    Ok(true)
+}
 
 #[tauri::command]
 pub async fn set_event_channel(
@@ -149,7 +384,7 @@ pub async fn get_battery_history_page(
     cursor: Option<i64>,
     size: u8,
 ) -> Result<Option<Vec<persis::HistoryInfo>>, ()> {
-    let now = Utc::now().timestamp();
+    let now = Utc::now().timestamp_millis();
     let state = state.lock().await;
     match &state.persis {
         Some(persis) => {
@@ -166,7 +401,7 @@ pub async fn get_battery_history_page(
 pub async fn get_battery_history(
     app_handle: tauri::AppHandle,
     state: State<'_, Arc<Mutex<session::SessionState>>>,
-    id: i64,
+    id: i32,
 ) -> Result<Option<persis::HistoryInfo>, ()> {
     let state = state.lock().await;
     match &state.persis {
@@ -177,3 +412,348 @@ pub async fn get_battery_history(
         None => Ok(None),
     }
 }
+#[command]
+pub async fn get_power_history(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+    start: i64,
+    end: i64,
+) -> Result<Option<Vec<persis::PowerPoint>>, String> {
+    let state = state.lock().await;
+    match &state.persis {
+        Some(persis) => {
+            let rows = persis
+                .select_power_range(start, end)
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(Some(rows))
+        }
+        None => Ok(None),
+    }
+}
+#[command]
+pub async fn get_battery_rollup(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+    start: i64,
+    end: i64,
+) -> Result<Option<Vec<persis::battery_one_hour::Model>>, String> {
+    let state = state.lock().await;
+    match &state.persis {
+        Some(persis) => {
+            let rows = persis
+                .select_battery_rollup(start, end)
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(Some(rows))
+        }
+        None => Ok(None),
+    }
+}
+#[command]
+pub async fn get_battery_series(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+    metrics: Vec<String>,
+    start: i64,
+    end: i64,
+    max_points: u32,
+) -> Result<Option<persis::Series>, String> {
+    let state = state.lock().await;
+    match &state.persis {
+        Some(persis) => {
+            let series = persis
+                .series(&metrics, start, end, max_points)
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(Some(series))
+        }
+        None => Ok(None),
+    }
+}
+#[command]
+pub async fn get_power_audit_page(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+    cursor: Option<i32>,
+    size: u8,
+) -> Result<Option<Vec<persis::power_limit_audit::Model>>, String> {
+    let mut state = state.lock().await;
+    state.flush_power_audit().await;
+    match &state.persis {
+        Some(persis) => {
+            let rows = persis
+                .select_power_audit_page(cursor, size)
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(Some(rows))
+        }
+        None => Ok(None),
+    }
+}
+#[command]
+pub async fn recommend_power_limit(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+    hours: f32,
+) -> Result<Option<persis::PowerRecommendation>, String> {
+    let state = state.lock().await;
+    let (cpu_family, capacity) = match (&state.power, &state.battery) {
+        (Some(power), Some(battery)) => (power.identifier.cpu_family, battery.capacity),
+        _ => return Ok(None),
+    };
+    match &state.persis {
+        Some(persis) => persis
+            .recommend_power_limit(cpu_family, capacity, hours, Utc::now().timestamp_millis())
+            .await
+            .map_err(|e| e.to_string()),
+        None => Ok(None),
+    }
+}
+#[command]
+pub async fn get_sleep_sessions(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+    start: i64,
+    end: i64,
+) -> Result<Option<Vec<persis::sleep_sessions::Model>>, String> {
+    let state = state.lock().await;
+    match &state.persis {
+        Some(persis) => {
+            let rows = persis
+                .select_sleep_sessions(start, end)
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(Some(rows))
+        }
+        None => Ok(None),
+    }
+}
+//当前电池的循环计数，驱动值与软件计数并列
+#[command]
+pub async fn get_battery_cycles(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+) -> Result<Option<persis::CycleHealth>, ()> {
+    let state = state.lock().await;
+    let (Some(battery), Some(persis)) = (&state.battery, &state.persis) else {
+        return Ok(None);
+    };
+    Ok(persis.cycle_health(battery.state_of_health))
+}
+#[command]
+pub async fn get_battery_cycle_list(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+) -> Result<Option<Vec<persis::battery_cycles::Model>>, String> {
+    let state = state.lock().await;
+    match &state.persis {
+        Some(persis) => {
+            let rows = persis
+                .select_battery_cycles()
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(Some(rows))
+        }
+        None => Ok(None),
+    }
+}
+//电量/温度区间的停留时长及日历老化压力
+#[command]
+pub async fn get_battery_stress(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+) -> Result<Option<persis::StressHistogram>, String> {
+    let state = state.lock().await;
+    match &state.persis {
+        Some(persis) => {
+            let histogram = persis.battery_stress().await.map_err(|e| e.to_string())?;
+            Ok(Some(histogram))
+        }
+        None => Ok(None),
+    }
+}
+#[command]
+pub async fn get_battery_debounce(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+) -> Result<battery::DebounceConfig, ()> {
+    let state = state.lock().await;
+    Ok(state.config.debounce)
+}
+#[command]
+pub async fn set_battery_debounce(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+    debounce: battery::DebounceConfig,
+) -> Result<bool, String> {
+    let mut state = state.lock().await;
+    state.config.debounce = debounce;
+    config::save_config(&state.config).map_err(|e| e.to_string())?;
+    if let Some(battery) = state.battery.as_mut() {
+        battery.set_debounce(debounce);
+    }
+    Ok(true)
+}
+//当前电池最近size天的综合健康度，按天倒序
+#[command]
+pub async fn get_battery_health(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+    size: u8,
+) -> Result<Option<Vec<persis::battery_health::Model>>, String> {
+    let state = state.lock().await;
+    let (Some(battery), Some(persis)) = (&state.battery, &state.persis) else {
+        return Ok(None);
+    };
+    let rows = persis
+        .select_health(&battery.key(), size as u64)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(Some(rows))
+}
+//写出电池报告，扩展名为.md/.markdown时为Markdown，其余为HTML
+#[command]
+pub async fn write_battery_report(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+    path: String,
+) -> Result<(), String> {
+    let mut state = state.lock().await;
+    let state = &mut *state;
+    let (Some(battery), Some(persis)) = (&state.battery, &mut state.persis) else {
+        return Err("battery history not available".to_string());
+    };
+    let report = persis
+        .battery_report(battery, Utc::now().timestamp_millis())
+        .await
+        .map_err(|e| e.to_string())?;
+    let path = std::path::Path::new(&path);
+    std::fs::write(path, report.render(persis::ReportFormat::from_path(path)))
+        .map_err(|e| e.to_string())
+}
+#[command]
+pub async fn get_health_alert(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+) -> Result<config::HealthAlertConfig, ()> {
+    let state = state.lock().await;
+    Ok(state.config.health_alert.clone())
+}
+#[command]
+pub async fn set_health_alert(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+    alert: config::HealthAlertConfig,
+) -> Result<bool, String> {
+    let mut state = state.lock().await;
+    state.config.health_alert = alert;
+    config::save_config(&state.config).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+//合并短于最短持续时间的历史段，返回删除的段数
+#[command]
+pub async fn merge_battery_history(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+    min_duration_ms: Option<i64>,
+) -> Result<Option<u64>, String> {
+    let state = state.lock().await;
+    let min_duration_ms = min_duration_ms.unwrap_or(state.config.debounce.min_dwell_ms);
+    match &state.persis {
+        Some(persis) => {
+            let removed = persis
+                .merge_micro_segments(min_duration_ms)
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(Some(removed))
+        }
+        None => Ok(None),
+    }
+}
+#[command]
+pub async fn get_retention_policy(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+) -> Result<persis::RetentionPolicy, ()> {
+    let state = state.lock().await;
+    Ok(state.config.retention)
+}
+#[command]
+pub async fn set_retention_policy(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+    retention: persis::RetentionPolicy,
+) -> Result<bool, String> {
+    retention.validate()?;
+    let mut state = state.lock().await;
+    state.config.retention = retention;
+    config::save_config(&state.config).map_err(|e| e.to_string())?;
+    if let Some(manager) = &mut state.persis {
+        manager.set_retention(retention).await?;
+    }
+    Ok(true)
+}
+#[command]
+pub async fn get_rules(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+) -> Result<Vec<rules::Rule>, ()> {
+    let state = state.lock().await;
+    Ok(state.config.rules.clone())
+}
+#[command]
+pub async fn set_rules(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+    rules: Vec<rules::Rule>,
+) -> Result<bool, String> {
+    rules::validate(&rules)?;
+    let mut state = state.lock().await;
+    state.config.rules = rules;
+    config::save_config(&state.config).expect("set_rules err.");
+    Ok(true)
+}
+//按当前状态求值但不执行动作，rules为空时使用已保存的规则
+#[command]
+pub async fn dry_run_rules(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+    rules: Option<Vec<rules::Rule>>,
+) -> Result<Vec<rules::FiredRule>, String> {
+    let state = state.lock().await;
+    let rules = rules.unwrap_or(state.config.rules.clone());
+    rules::validate(&rules)?;
+    let ctx = rules::RuleContext::from_session(&state);
+    Ok(state
+        .rule_engine
+        .evaluate(&rules, &ctx)
+        .into_iter()
+        .map(|x| rules::FiredRule {
+            timestamp: ctx.timestamp,
+            rule: x.name.clone(),
+            actions: x.actions.clone(),
+            dry_run: true,
+            errors: Vec::new(),
+        })
+        .collect())
+}
+#[command]
+pub async fn get_rule_log(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+) -> Result<Vec<rules::FiredRule>, ()> {
+    let state = state.lock().await;
+    Ok(state.rule_engine.log.iter().rev().cloned().collect())
+}
+#[command]
+pub async fn get_power_lock(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<session::SessionState>>>,
+) -> Result<(config::PowerLockConfig, Option<String>), ()> {
+    let state = state.lock().await;
+    Ok((
+        state.config.power_lock.clone(),
+        state.power_lock.warning.clone(),
+    ))
+}
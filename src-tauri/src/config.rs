@@ -1,3 +1,4 @@
+use crate::rules;
 use serde::{Deserialize, Serialize};
 use std::{
     env,
@@ -17,13 +18,64 @@ fn check_if_dev() -> bool {
 }
 
 // 定义配置结构体
-#[derive(Serialize, Deserialize, Clone, Copy)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Config {
     pub auto_start: bool,             // 系统启动时自动启动
     pub start_minimize: bool,         //启动时最小化
     pub ui_update: u8,                // UI标更新时间
     pub service_update: u8,           // 监控服务更新时间
     pub record_battery_history: bool, // 是否记录电池活动历史
+    #[serde(default)]
+    pub power_presets: Vec<power::Preset>, // 功率预设，由预设相关命令维护
+    #[serde(default)]
+    pub rules: Vec<rules::Rule>, // 自动化规则，由规则相关命令维护
+    #[serde(default)]
+    pub power_lock: PowerLockConfig, // 功率锁定状态，重启后恢复
+    #[serde(default)]
+    pub governor: power::GovernorConfig, // 温度/放电功率闭环调节，与功率锁定互斥
+    #[serde(default)]
+    pub retention: persis::RetentionPolicy, // 历史数据各层的保留时长
+    #[serde(default)]
+    pub restore_on_exit: bool, // 退出时恢复出厂功率
+    #[serde(default)]
+    pub sample_interval_ms: u16, // 电池采样间隔(毫秒)，0表示与后台更新间隔相同
+    #[serde(default)]
+    pub debounce: battery::DebounceConfig, // 电池状态切换的去抖参数
+    #[serde(default)]
+    pub health_alert: HealthAlertConfig, // 综合健康度的提醒阈值
+}
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct PowerLockConfig {
+    pub enable: bool,
+    pub limit: power::PowerLimit,
+    pub preset: Option<String>,
+}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HealthAlertConfig {
+    //综合健康度(0-100)降到以下值时提醒
+    pub score_milestones: Vec<f64>,
+    //电池健康度(0-1)降到以下值时提醒
+    pub soh_thresholds: Vec<f32>,
+}
+impl Default for HealthAlertConfig {
+    fn default() -> Self {
+        Self {
+            score_milestones: vec![80.0, 60.0, 40.0],
+            soh_thresholds: vec![0.9, 0.8],
+        }
+    }
+}
+impl Config {
+    // 由其他命令维护的字段，设置页保存时保留当前值
+    pub fn keep_managed(&mut self, current: &Config) {
+        self.power_presets = current.power_presets.clone();
+        self.rules = current.rules.clone();
+        self.power_lock = current.power_lock.clone();
+        self.governor = current.governor;
+        self.retention = current.retention;
+        self.debounce = current.debounce;
+        self.health_alert = current.health_alert.clone();
+    }
 }
 
 impl Default for Config {
@@ -34,6 +86,15 @@ impl Default for Config {
             ui_update: 2,
             service_update: 1,
             record_battery_history: true,
+            power_presets: Vec::new(),
+            rules: Vec::new(),
+            power_lock: PowerLockConfig::default(),
+            governor: power::GovernorConfig::default(),
+            retention: persis::RetentionPolicy::default(),
+            restore_on_exit: false,
+            sample_interval_ms: 0,
+            debounce: battery::DebounceConfig::default(),
+            health_alert: HealthAlertConfig::default(),
         }
     }
 }
@@ -84,6 +145,32 @@ pub fn save_config(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+// 应用功率限制后写入的标记，稳定运行一段时间或正常退出后删除
+// 启动时若仍存在，说明上次会话在设置功率后不久崩溃
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PowerGuard {
+    pub applied_at: i64,
+    pub limit: power::PowerLimit,
+}
+pub fn get_power_guard_path() -> PathBuf {
+    get_exe_directory().join("power_guard.json")
+}
+pub fn load_power_guard() -> Option<PowerGuard> {
+    let contents = std::fs::read_to_string(get_power_guard_path()).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+pub fn save_power_guard(guard: &PowerGuard) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = File::create(get_power_guard_path())?;
+    file.write_all(serde_json::to_string(guard)?.as_bytes())?;
+    Ok(())
+}
+pub fn clear_power_guard() {
+    let path = get_power_guard_path();
+    if path.exists() {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
 pub fn set_autostart(app_handle: &tauri::AppHandle, val: bool) {
     let autostart_manager = app_handle.autolaunch();
     if !check_if_dev() {
@@ -0,0 +1,297 @@
+use crate::config;
+use crate::session::SessionState;
+use log::{log, Level};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+//每个阶段开始后丢弃的秒数，等待温度和功率稳定
+const WARMUP_SECS: i64 = 30;
+const MIN_INTERVAL_SECS: u32 = 60;
+const METRICS: [&str; 3] = ["drain", "temperature", "cpu_load"];
+
+//按顺序轮流应用presets，每个预设每轮运行interval_secs秒
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ExperimentPlan {
+    pub presets: Vec<String>,
+    pub interval_secs: u32,
+    pub rounds: u32,
+}
+impl ExperimentPlan {
+    pub fn validate(&self, state: &SessionState) -> Result<(), String> {
+        if self.presets.len() < 2 {
+            return Err("experiment needs at least 2 presets".to_string());
+        }
+        let mut names = HashSet::new();
+        for name in &self.presets {
+            if !names.insert(name) {
+                return Err(format!("duplicate preset({})", name));
+            }
+            if state.find_power_preset(name).is_none() {
+                return Err(format!("preset({}) not found", name));
+            }
+        }
+        if self.interval_secs < MIN_INTERVAL_SECS {
+            return Err(format!("interval must be >= {}s", MIN_INTERVAL_SECS));
+        }
+        //每个预设至少需要2个阶段才能计算置信区间
+        if self.rounds < 2 {
+            return Err("experiment needs at least 2 rounds".to_string());
+        }
+        Ok(())
+    }
+}
+#[derive(Serialize, Clone, Debug)]
+pub struct ExperimentProgress {
+    pub plan: ExperimentPlan,
+    pub started_at: i64,
+    pub phase: usize,
+    pub total_phases: usize,
+    pub preset: String,
+    pub phase_started_at: i64,
+}
+//实验开始前的状态，结束或中止后恢复
+pub struct ExperimentRestore {
+    pub limit: power::PowerLimit,
+    //实验期间暂停的功率锁定和闭环调节，未启用时为None
+    pub lock: Option<config::PowerLockConfig>,
+    pub governor: Option<power::GovernorConfig>,
+}
+impl ExperimentRestore {
+    //记录实验前所有可回读的功率设置，没有功率信息时为None
+    pub fn capture(state: &SessionState) -> Option<Self> {
+        let info = state.power.as_ref()?;
+        Some(Self {
+            limit: info.current_limit(),
+            lock: state
+                .power_lock
+                .enable
+                .then(|| state.config.power_lock.clone()),
+            governor: state.governor.is_some().then_some(state.config.governor),
+        })
+    }
+}
+pub struct Experiment {
+    pub plan: ExperimentPlan,
+    pub started_at: i64,
+    pub restore: ExperimentRestore,
+    phase: usize,
+    //当前阶段已应用预设的时间
+    phase_started_at: Option<i64>,
+    //当前阶段的秒级样本
+    current: HashMap<&'static str, Vec<f32>>,
+    //每个预设各阶段的均值
+    phase_means: HashMap<String, HashMap<&'static str, Vec<f32>>>,
+}
+impl Experiment {
+    pub fn new(plan: ExperimentPlan, now: i64, restore: ExperimentRestore) -> Self {
+        Self {
+            plan,
+            started_at: now,
+            restore,
+            phase: 0,
+            phase_started_at: None,
+            current: HashMap::new(),
+            phase_means: HashMap::new(),
+        }
+    }
+    fn total_phases(&self) -> usize {
+        self.plan.presets.len() * self.plan.rounds as usize
+    }
+    fn preset(&self) -> &str {
+        &self.plan.presets[self.phase % self.plan.presets.len()]
+    }
+    pub fn progress(&self) -> ExperimentProgress {
+        ExperimentProgress {
+            plan: self.plan.clone(),
+            started_at: self.started_at,
+            phase: self.phase,
+            total_phases: self.total_phases(),
+            preset: self.preset().to_string(),
+            phase_started_at: self.phase_started_at.unwrap_or(self.started_at),
+        }
+    }
+    fn observe(&mut self, state: &SessionState) {
+        let drain = state
+            .battery
+            .as_ref()
+            .and_then(|x| match x.energy_rate < 0.0 {
+                true => Some(-x.energy_rate),
+                false => None,
+            });
+        let temperature = state.power.as_ref().and_then(|x| x.tctl_temp_value);
+        for (metric, value) in METRICS
+            .iter()
+            .zip([drain, temperature, Some(state.system.cpuload)])
+        {
+            if let Some(value) = value {
+                self.current.entry(metric).or_default().push(value);
+            }
+        }
+    }
+    fn finish_phase(&mut self) {
+        let preset = self.preset().to_string();
+        let means = self.phase_means.entry(preset).or_default();
+        for (metric, values) in self.current.drain() {
+            if !values.is_empty() {
+                let mean = values.iter().sum::<f32>() / values.len() as f32;
+                means.entry(metric).or_default().push(mean);
+            }
+        }
+        self.phase += 1;
+        self.phase_started_at = None;
+    }
+    fn report(&self, now: i64) -> persis::ExperimentReport {
+        let mut summaries = Vec::new();
+        for preset in &self.plan.presets {
+            for metric in METRICS {
+                let stats = self
+                    .phase_means
+                    .get(preset)
+                    .and_then(|x| x.get(metric))
+                    .and_then(|x| persis::MetricStats::from_values(x));
+                if let Some(stats) = stats {
+                    summaries.push(persis::ExperimentSummary {
+                        preset: preset.clone(),
+                        metric: metric.to_string(),
+                        stats,
+                    });
+                }
+            }
+        }
+        //实验按秒计时，入库时与其他表一样使用毫秒
+        persis::ExperimentReport {
+            started_at: persis::secs_to_ms(self.started_at),
+            ended_at: persis::secs_to_ms(now),
+            interval_secs: self.plan.interval_secs,
+            rounds: self.plan.rounds,
+            summaries,
+        }
+    }
+}
+//每个服务循环调用一次，实验结束时返回报告
+pub fn tick(
+    state: &mut SessionState,
+    now: i64,
+) -> Result<Option<persis::ExperimentReport>, String> {
+    let mut experiment = match state.experiment.take() {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+    let started_at = match experiment.phase_started_at {
+        Some(v) => v,
+        None => {
+            let name = experiment.preset().to_string();
+            if let Err(e) = apply_preset(state, &name) {
+                stop(state, experiment);
+                return Err(e);
+            }
+            experiment.phase_started_at = Some(now);
+            now
+        }
+    };
+    if now - started_at >= WARMUP_SECS {
+        experiment.observe(state);
+    }
+    if now - started_at >= experiment.plan.interval_secs as i64 {
+        experiment.finish_phase();
+        if experiment.phase >= experiment.total_phases() {
+            let report = experiment.report(now);
+            log!(
+                Level::Info,
+                "experiment finished:{}",
+                experiment.plan.presets.join(",")
+            );
+            stop(state, experiment);
+            return Ok(Some(report));
+        }
+    }
+    state.experiment = Some(experiment);
+    Ok(None)
+}
+fn apply_preset(state: &mut SessionState, name: &str) -> Result<(), String> {
+    let preset = state
+        .find_power_preset(name)
+        .ok_or(format!("preset({}) not found", name))?;
+    state
+        .apply_power_limit(
+            &preset.limit,
+            persis::PowerAuditSource::Experiment,
+            Some(name.to_string()),
+        )
+        .map_err(|e| e.to_string())?;
+    state.set_active_preset(Some(name.to_string()));
+    Ok(())
+}
+//恢复实验前的功率，以及实验开始时暂停的功率锁定或闭环调节
+pub fn stop(state: &mut SessionState, experiment: Experiment) {
+    let restore = experiment.restore;
+    state.set_active_preset(None);
+    if let Err(e) =
+        state.apply_power_limit(&restore.limit, persis::PowerAuditSource::Experiment, None)
+    {
+        log!(Level::Error, "experiment restore limit err:{}", e);
+    }
+    if let Some(lock) = restore.lock {
+        state.set_power_lock(true, lock.limit, lock.preset);
+        state.power_lock.lastcheck = 0;
+    } else if let Some(governor) = restore.governor {
+        state.set_governor(governor);
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restore_full_limit() {
+        let mut state = SessionState::default();
+        state.power = Some(power::Status {
+            stapm_limit: 15.0,
+            slow_limit: 20.0,
+            fast_limit: 25.0,
+            tctl_temp_limit: Some(85.0),
+            apu_skin_temp_limit: Some(45.0),
+            vrm_current: Some(60.0),
+            stapm_time: Some(200.0),
+            slow_time: Some(5.0),
+            ..Default::default()
+        });
+        for (name, tctl_temp, stapm_time) in [("cool", 70, 100), ("fast", 95, 300)] {
+            state.config.power_presets.push(power::Preset {
+                name: name.to_string(),
+                limit: power::PowerLimit {
+                    stapm_limit: 10.0,
+                    slow_limit: 12.0,
+                    fast_limit: 15.0,
+                    tctl_temp: Some(tctl_temp),
+                    stapm_time: Some(stapm_time),
+                    ..Default::default()
+                },
+            });
+        }
+        let plan = ExperimentPlan {
+            presets: vec!["cool".to_string(), "fast".to_string()],
+            interval_secs: 60,
+            rounds: 2,
+        };
+        let restore = ExperimentRestore::capture(&state).unwrap();
+        state.experiment = Some(Experiment::new(plan, 0, restore));
+        //非管理员时不写入硬件，按当前阶段的预设模拟回读的功率
+        for now in [0, 60, 120] {
+            assert!(tick(&mut state, now).unwrap().is_none());
+            let name = state.experiment.as_ref().unwrap().preset().to_string();
+            let limit = state.find_power_preset(&name).unwrap().limit;
+            let info = state.power.as_mut().unwrap();
+            info.stapm_limit = limit.stapm_limit;
+            info.tctl_temp_limit = limit.tctl_temp.map(|x| x as f32);
+            info.stapm_time = limit.stapm_time.map(|x| x as f32);
+        }
+        let limit = state.experiment.as_ref().unwrap().restore.limit;
+        assert_eq!(limit.stapm_limit, 15.0);
+        assert_eq!(limit.tctl_temp, Some(85));
+        assert_eq!(limit.apu_skin_temp, Some(45));
+        assert_eq!(limit.vrm_current, Some(60.0));
+        assert_eq!(limit.stapm_time, Some(200));
+        assert_eq!(limit.slow_time, Some(5));
+    }
+}
@@ -9,16 +9,51 @@ use tokio::sync::{oneshot, Mutex};
 use tokio::time::{sleep, Duration};
 mod commands;
 mod config;
+mod experiment;
+mod rules;
 mod session;
 mod tray;
 mod windows;
 //mod processor;
+//只读打开history.db，不经过Manager，不修改正在运行的实例的记录及表结构
+fn write_battery_report(path: &str) -> Result<(), String> {
+    use status::Status;
+    let battery = battery::Status::build()
+        .and_then(|x| x.into_iter().next())
+        .ok_or("no battery found")?;
+    tokio::task::block_in_place(|| {
+        tokio::runtime::Handle::current().block_on(persis::write_battery_report(
+            &config::get_exe_directory().join("history.db"),
+            (&battery).into(),
+            std::path::Path::new(path),
+            Utc::now().timestamp_millis(),
+        ))
+    })
+}
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     const TRAY_ID: &str = "main";
     let args: Vec<String> = env::args().collect();
     let is_autostart = args.contains(&"--autostart".to_string());
     let is_adminstart = args.contains(&"--adminstart".to_string());
+    //--battery-report <path>：写出电池报告后退出，不启动界面
+    if let Some(i) = args.iter().position(|x| x == "--battery-report") {
+        windows::attach_console();
+        let result = match args.get(i + 1) {
+            Some(path) => write_battery_report(path),
+            None => Err("usage: --battery-report <path>".to_string()),
+        };
+        match result {
+            Ok(()) => {
+                println!("battery report written to {}", args[i + 1]);
+                std::process::exit(0);
+            }
+            Err(e) => {
+                eprintln!("battery report err:{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
     panic::set_hook(Box::new(|info| {
         let payload = info.payload();
         let message = if let Some(s) = payload.downcast_ref::<&str>() {
@@ -74,7 +109,7 @@ pub fn run() {
         .setup(move |app| {
             log!(Level::Info, "args ={:?}", args);
             let config = config::load_config().expect("load_config err.");
-            let session = session::SessionState::new(config);
+            let session = session::SessionState::new(config.clone());
             app.manage(Arc::new(Mutex::new(session)));
             if is_adminstart {
                 windows::active_window(app.handle(), "main");
@@ -95,6 +130,7 @@ pub fn run() {
                 let mut tx = Some(tx); // 将 tx 包装成 Option，以便在第一次发送后取出
                 loop {
                     let mut secs = 1;
+                    let mut sample_ms = 0;
                     {
                         let state = handler1.state::<Arc<Mutex<session::SessionState>>>();
                         let mut state = state.lock().await;
@@ -102,9 +138,9 @@ pub fn run() {
                         state.system.last();
                         //battery
                         if state.battery.is_some() {
-                            let battery = state.battery.as_mut().unwrap();
-                            let last_state = battery.state.clone();
-                            battery.last();
+                            let last_state = state.battery.as_ref().unwrap().state;
+                            state.refresh_battery().await;
+                            let battery = state.battery.as_ref().unwrap();
                             if battery.state_changed {
                                 log!(
                                     Level::Warn,
@@ -124,7 +160,10 @@ pub fn run() {
                             }
                         }
                         //store
-                        if state.config.record_battery_history && state.battery.is_some() {
+                        if state.config.record_battery_history
+                            && state.battery.is_some()
+                            && !state.exiting
+                        {
                             if state.persis.is_none() {
                                 state.persis = Some(
                                     persis::Manager::build(
@@ -138,15 +177,25 @@ pub fn run() {
                                     .await
                                     .unwrap(),
                                 );
+                                let retention = state.config.retention;
+                                if let Some(manager) = &mut state.persis {
+                                    if let Err(e) = manager.set_retention(retention).await {
+                                        log!(Level::Error, "set retention err:{}", e);
+                                    }
+                                }
                             }
                         } else {
                             if let Some(ref mut manager) = state.persis {
+                                if let Err(e) = manager.flush().await {
+                                    log!(Level::Error, "persis flush err:{}", e);
+                                }
                                 manager.close().await;
                                 state.persis = None;
                             }
                         }
                         let battery = state.battery.clone().unwrap();
                         let system = state.system.clone();
+                        let power = state.power.clone();
                         if let Some(manager) = &mut state.persis {
                             if battery.state_changed {
                                 log!(
@@ -156,6 +205,11 @@ pub fn run() {
                                     battery.state
                                 );
                             }
+                            if let Some(power) = &power {
+                                if let Err(e) = manager.insert_power(power).await {
+                                    log!(Level::Error, "manager.insert_power error:{}", e);
+                                }
+                            }
                             let res = manager
                                 .insert_battery(&battery, &system, |_| async {
                                     log!(Level::Warn, "new battery history ");
@@ -180,6 +234,32 @@ pub fn run() {
                                 }
                             }
                         }
+                        //health
+                        state.check_health(&handler1).await;
+                        //rules
+                        let ctx = rules::RuleContext::from_session(&state);
+                        state.rule_engine.observe(&ctx);
+                        let rules = state.config.rules.clone();
+                        let triggered = state.rule_engine.triggered(&rules, &ctx);
+                        for rule in triggered {
+                            let fired = rules::execute(&mut state, &handler1, &rule);
+                            state.rule_engine.record(fired);
+                        }
+                        //experiment
+                        match experiment::tick(&mut state, Utc::now().timestamp()) {
+                            Ok(Some(report)) => {
+                                if let Some(manager) = &state.persis {
+                                    if let Err(e) = manager.insert_experiment(&report).await {
+                                        log!(Level::Error, "insert experiment err:{}", e);
+                                    }
+                                }
+                                state.experiment_report = Some(report);
+                            }
+                            Ok(None) => (),
+                            Err(e) => log!(Level::Error, "experiment aborted:{}", e),
+                        }
+                        //governor
+                        state.tick_governor(Utc::now().timestamp());
                         //power_lock
                         if state.power_lock.enable
                             && state.is_admin
@@ -195,15 +275,22 @@ pub fn run() {
                                         || info.slow_limit != limit.slow_limit
                                         || info.stapm_limit != limit.stapm_limit
                                     {
-                                        match power::set_limit(&limit) {
-                                            Ok(_) => {
-                                                {
-                                                    info.last();
-                                                }
+                                        let preset = state.config.power_lock.preset.clone();
+                                        match state.apply_power_limit(
+                                            &limit,
+                                            persis::PowerAuditSource::PowerLock,
+                                            preset,
+                                        ) {
+                                            Ok((_, info)) => {
                                                 log!(Level::Warn, "in loop,set_limit:{:?}", info);
                                             }
                                             Err(err) => {
-                                                state.power_lock.enable = false;
+                                                let saved = state.config.power_lock.clone();
+                                                state.set_power_lock(
+                                                    false,
+                                                    saved.limit,
+                                                    saved.preset,
+                                                );
                                                 log!(
                                                     Level::Error,
                                                     "in loop,set_limit err:{:?}",
@@ -218,16 +305,33 @@ pub fn run() {
                                 }
                             }
                         }
+                        state.check_power_guard(Utc::now().timestamp());
+                        state.flush_power_audit().await;
                         //store
                         //processor.update(&state);
                         session::EventChannel::emit_service_update(&handler1, &state);
                         secs = state.config.service_update;
+                        if state.config.sample_interval_ms > 0 {
+                            sample_ms = (state.config.sample_interval_ms as u64)
+                                .max(persis::MIN_SAMPLE_INTERVAL_MS as u64);
+                        }
                     }
 
                     if let Some(t) = tx.take() {
                         let _ = t.send(()).map_err(|_| ());
                     }
-                    sleep(Duration::from_secs(secs as u64)).await;
+                    //两次后台更新之间按sample_interval_ms额外采样电池
+                    let mut waited = 0;
+                    while sample_ms > 0 && waited + sample_ms < secs as u64 * 1000 {
+                        sleep(Duration::from_millis(sample_ms)).await;
+                        waited += sample_ms;
+                        let state = handler1.state::<Arc<Mutex<session::SessionState>>>();
+                        let mut state = state.lock().await;
+                        if state.sample_battery().await {
+                            session::EventChannel::emit_history_update(&handler1, &state);
+                        }
+                    }
+                    sleep(Duration::from_millis(secs as u64 * 1000 - waited)).await;
                 }
             });
             //ui update.
@@ -261,11 +365,48 @@ pub fn run() {
             commands::exec_elevate_self,
             commands::set_power_limit,
             commands::set_power_limit_lock,
+            commands::restore_power_limit,
+            commands::get_power_factory_limit,
+            commands::get_power_limit_range,
+            commands::get_power_presets,
+            commands::save_power_preset,
+            commands::delete_power_preset,
+            commands::apply_power_preset,
+            commands::lock_power_preset,
+            commands::get_rules,
+            commands::set_rules,
+            commands::dry_run_rules,
+            commands::get_rule_log,
+            commands::get_power_lock,
+            commands::get_governor,
+            commands::set_governor,
+            commands::start_experiment,
+            commands::stop_experiment,
+            commands::get_experiment,
+            commands::get_experiments,
+            commands::get_retention_policy,
+            commands::set_retention_policy,
             commands::get_system,
             commands::set_event_channel,
             commands::get_battery,
             commands::get_battery_history_page,
             commands::get_battery_history,
+            commands::get_battery_debounce,
+            commands::set_battery_debounce,
+            commands::merge_battery_history,
+            commands::get_power_history,
+            commands::get_battery_rollup,
+            commands::get_battery_series,
+            commands::get_sleep_sessions,
+            commands::get_battery_cycles,
+            commands::get_battery_cycle_list,
+            commands::get_battery_stress,
+            commands::get_battery_health,
+            commands::get_health_alert,
+            commands::set_health_alert,
+            commands::write_battery_report,
+            commands::get_power_audit_page,
+            commands::recommend_power_limit,
         ])
         .on_window_event(|window, event| {
             if let tauri::WindowEvent::CloseRequested { api, .. } = event {
@@ -284,6 +425,19 @@ pub fn run() {
                 });
             }
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                //托盘以外的退出路径也按配置恢复出厂功率
+                if let Some(state) = app.try_state::<Arc<Mutex<session::SessionState>>>() {
+                    tokio::task::block_in_place(|| {
+                        tokio::runtime::Handle::current()
+                            .block_on(async { state.lock().await.prepare_exit().await })
+                    });
+                }
+                //正常退出，不视为设置功率后崩溃
+                config::clear_power_guard();
+            }
+        });
 }
@@ -0,0 +1,439 @@
+use crate::session::{EventChannel, SessionState};
+use chrono::prelude::*;
+use log::{log, Level};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use tauri::AppHandle;
+
+//持续cpu占用条件最长的观察窗口
+const MAX_LOAD_WINDOW_SECS: i64 = 3600;
+//保留的触发日志条数
+const MAX_LOG_SIZE: usize = 200;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Sensor {
+    Battery,
+    Cpu,
+}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Condition {
+    AcPlugged,
+    AcUnplugged,
+    //电量百分比(0-100)
+    BatteryBelow { percentage: f32 },
+    BatteryAbove { percentage: f32 },
+    //本地时间窗口，单位为当天的分钟数，start>end时跨越午夜
+    TimeBetween { start: u16, end: u16 },
+    TemperatureAbove { sensor: Sensor, celsius: f32 },
+    //cpu占用(0-100)在secs秒内持续高于load
+    CpuLoadAbove { load: f32, secs: u32 },
+}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Action {
+    ApplyPreset { name: String },
+    LockPreset { name: String },
+    Unlock,
+    SetChargeThreshold { percentage: u8 },
+    Notify { message: String },
+}
+//所有条件同时满足时执行全部动作
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Rule {
+    pub name: String,
+    pub enable: bool,
+    pub conditions: Vec<Condition>,
+    pub actions: Vec<Action>,
+}
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FiredRule {
+    pub timestamp: i64,
+    pub rule: String,
+    pub actions: Vec<Action>,
+    pub dry_run: bool,
+    pub errors: Vec<String>,
+}
+//规则求值所需的输入快照
+#[derive(Serialize, Clone, Debug)]
+pub struct RuleContext {
+    pub timestamp: i64,
+    pub minute_of_day: u16,
+    pub ac_plugged: Option<bool>,
+    pub percentage: Option<f32>,
+    pub battery_temperature: Option<f32>,
+    pub cpu_temperature: Option<f32>,
+    pub cpu_load: f32,
+}
+impl RuleContext {
+    pub fn from_session(state: &SessionState) -> Self {
+        let now = Local::now();
+        let battery = state.battery.as_ref();
+        Self {
+            timestamp: now.timestamp(),
+            minute_of_day: (now.hour() * 60 + now.minute()) as u16,
+            ac_plugged: battery.map(|x| {
+                !matches!(
+                    x.state,
+                    battery::State(battery::ExternalBatteryState::Discharging)
+                        | battery::State(battery::ExternalBatteryState::Empty)
+                )
+            }),
+            percentage: battery.map(|x| x.percentage * 100.0),
+            battery_temperature: battery.and_then(|x| x.temperature),
+            cpu_temperature: state.power.as_ref().and_then(|x| x.tctl_temp_value),
+            cpu_load: state.system.cpuload * 100.0,
+        }
+    }
+}
+pub fn validate(rules: &[Rule]) -> Result<(), String> {
+    let mut names = HashSet::new();
+    for rule in rules {
+        if !names.insert(rule.name.as_str()) {
+            return Err(format!("duplicate rule name({})", rule.name));
+        }
+        if rule.conditions.is_empty() || rule.actions.is_empty() {
+            return Err(format!("rule({}) has no condition or action", rule.name));
+        }
+        for condition in &rule.conditions {
+            match condition {
+                Condition::TimeBetween { start, end } if *start >= 24 * 60 || *end >= 24 * 60 => {
+                    return Err(format!("rule({}) has invalid time window", rule.name));
+                }
+                //超过观察窗口的条件永远不会成立
+                Condition::CpuLoadAbove { secs, .. } if *secs as i64 > MAX_LOAD_WINDOW_SECS => {
+                    return Err(format!(
+                        "rule({}) cpu load window exceeds {}s",
+                        rule.name, MAX_LOAD_WINDOW_SECS
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct RuleEngine {
+    load_samples: VecDeque<(i64, f32)>,
+    //上次求值时条件成立的规则，用于边沿触发
+    active: HashSet<String>,
+    pub log: VecDeque<FiredRule>,
+}
+impl RuleEngine {
+    pub fn new() -> Self {
+        RuleEngine::default()
+    }
+    pub fn observe(&mut self, ctx: &RuleContext) {
+        self.load_samples.push_back((ctx.timestamp, ctx.cpu_load));
+        while let Some((timestamp, _)) = self.load_samples.front() {
+            if ctx.timestamp - timestamp > MAX_LOAD_WINDOW_SECS {
+                self.load_samples.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+    fn matches(&self, condition: &Condition, ctx: &RuleContext) -> bool {
+        match condition {
+            Condition::AcPlugged => ctx.ac_plugged == Some(true),
+            Condition::AcUnplugged => ctx.ac_plugged == Some(false),
+            Condition::BatteryBelow { percentage } => {
+                ctx.percentage.is_some_and(|x| x < *percentage)
+            }
+            Condition::BatteryAbove { percentage } => {
+                ctx.percentage.is_some_and(|x| x > *percentage)
+            }
+            Condition::TimeBetween { start, end } => {
+                let m = ctx.minute_of_day;
+                if start <= end {
+                    m >= *start && m < *end
+                } else {
+                    m >= *start || m < *end
+                }
+            }
+            Condition::TemperatureAbove { sensor, celsius } => match sensor {
+                Sensor::Battery => ctx.battery_temperature.is_some_and(|x| x > *celsius),
+                Sensor::Cpu => ctx.cpu_temperature.is_some_and(|x| x > *celsius),
+            },
+            Condition::CpuLoadAbove { load, secs } => {
+                let start = ctx.timestamp - *secs as i64;
+                //样本必须覆盖整个窗口
+                match self.load_samples.front() {
+                    Some((first, _)) if *first <= start => self
+                        .load_samples
+                        .iter()
+                        .filter(|(timestamp, _)| *timestamp >= start)
+                        .all(|(_, x)| x > load),
+                    _ => false,
+                }
+            }
+        }
+    }
+    //当前所有条件成立的规则
+    pub fn evaluate<'a>(&self, rules: &'a [Rule], ctx: &RuleContext) -> Vec<&'a Rule> {
+        rules
+            .iter()
+            .filter(|x| x.enable && x.conditions.iter().all(|c| self.matches(c, ctx)))
+            .collect()
+    }
+    //只返回由不成立变为成立的规则，条件持续成立时不会重复触发
+    pub fn triggered(&mut self, rules: &[Rule], ctx: &RuleContext) -> Vec<Rule> {
+        let matched: Vec<Rule> = self.evaluate(rules, ctx).into_iter().cloned().collect();
+        let fired = matched
+            .iter()
+            .filter(|x| !self.active.contains(&x.name))
+            .cloned()
+            .collect();
+        self.active = matched.into_iter().map(|x| x.name).collect();
+        fired
+    }
+    pub fn record(&mut self, fired: FiredRule) {
+        self.log.push_back(fired);
+        while self.log.len() > MAX_LOG_SIZE {
+            self.log.pop_front();
+        }
+    }
+}
+fn execute_action(
+    state: &mut SessionState,
+    handler: &AppHandle,
+    rule: &Rule,
+    action: &Action,
+) -> Result<(), String> {
+    //实验期间暂停修改功率的动作，避免干扰测量
+    let changes_power = matches!(
+        action,
+        Action::ApplyPreset { .. } | Action::LockPreset { .. } | Action::Unlock
+    );
+    if changes_power && state.experiment.is_some() {
+        return Err("paused while an experiment is running".to_string());
+    }
+    match action {
+        Action::ApplyPreset { name } => {
+            let preset = state
+                .find_power_preset(name)
+                .ok_or(format!("preset({}) not found", name))?;
+            let (applied, _) = state
+                .apply_power_limit(
+                    &preset.limit,
+                    persis::PowerAuditSource::Rule,
+                    Some(rule.name.clone()),
+                )
+                .map_err(|e| e.to_string())?;
+            if !applied {
+                return Err(format!("preset({}) not applied", name));
+            }
+            state.set_active_preset(Some(name.clone()));
+        }
+        Action::LockPreset { name } => {
+            let preset = state
+                .find_power_preset(name)
+                .ok_or(format!("preset({}) not found", name))?;
+            state.set_power_lock(true, preset.limit, Some(name.clone()));
+            //下次循环立即检查
+            state.power_lock.lastcheck = 0;
+        }
+        Action::Unlock => {
+            let saved = state.config.power_lock.clone();
+            state.set_power_lock(false, saved.limit, saved.preset);
+        }
+        Action::SetChargeThreshold { percentage } => {
+            battery::set_charge_threshold(*percentage)?;
+        }
+        Action::Notify { message } => {
+            EventChannel::emit_rule_notification(handler, &rule.name, message);
+        }
+    }
+    Ok(())
+}
+pub fn execute(state: &mut SessionState, handler: &AppHandle, rule: &Rule) -> FiredRule {
+    let mut errors = Vec::new();
+    for action in &rule.actions {
+        if let Err(e) = execute_action(state, handler, rule, action) {
+            log!(
+                Level::Error,
+                "rule({}) action {:?} err:{}",
+                rule.name,
+                action,
+                e
+            );
+            errors.push(e);
+        }
+    }
+    log!(Level::Warn, "rule({}) fired", rule.name);
+    FiredRule {
+        timestamp: Utc::now().timestamp(),
+        rule: rule.name.clone(),
+        actions: rule.actions.clone(),
+        dry_run: false,
+        errors,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(timestamp: i64, minute_of_day: u16, cpu_load: f32) -> RuleContext {
+        RuleContext {
+            timestamp,
+            minute_of_day,
+            ac_plugged: Some(false),
+            percentage: Some(30.0),
+            battery_temperature: Some(40.0),
+            cpu_temperature: None,
+            cpu_load,
+        }
+    }
+    fn rule(name: &str, conditions: Vec<Condition>) -> Rule {
+        Rule {
+            name: name.to_string(),
+            enable: true,
+            conditions,
+            actions: vec![Action::Notify {
+                message: name.to_string(),
+            }],
+        }
+    }
+    #[test]
+    fn condition_matching() {
+        let engine = RuleEngine::new();
+        let ctx = context(0, 600, 0.0);
+        let rules = [
+            rule(
+                "unplugged_low",
+                vec![
+                    Condition::AcUnplugged,
+                    Condition::BatteryBelow { percentage: 40.0 },
+                ],
+            ),
+            rule("plugged", vec![Condition::AcPlugged]),
+            rule(
+                "hot_battery",
+                vec![Condition::TemperatureAbove {
+                    sensor: Sensor::Battery,
+                    celsius: 35.0,
+                }],
+            ),
+            //没有读数时不成立
+            rule(
+                "hot_cpu",
+                vec![Condition::TemperatureAbove {
+                    sensor: Sensor::Cpu,
+                    celsius: 0.0,
+                }],
+            ),
+            Rule {
+                enable: false,
+                ..rule("disabled", vec![Condition::AcUnplugged])
+            },
+        ];
+        let names: Vec<&str> = engine
+            .evaluate(&rules, &ctx)
+            .iter()
+            .map(|x| x.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["unplugged_low", "hot_battery"]);
+    }
+    #[test]
+    fn time_between_wraps_midnight() {
+        let engine = RuleEngine::new();
+        let night = Condition::TimeBetween {
+            start: 22 * 60,
+            end: 6 * 60,
+        };
+        let day = Condition::TimeBetween {
+            start: 9 * 60,
+            end: 18 * 60,
+        };
+        for (minute, at_night, at_day) in [
+            (23 * 60, true, false),
+            (0, true, false),
+            (6 * 60 - 1, true, false),
+            (6 * 60, false, false),
+            (12 * 60, false, true),
+            (18 * 60, false, false),
+            (22 * 60, true, false),
+        ] {
+            let ctx = context(0, minute, 0.0);
+            assert_eq!(engine.matches(&night, &ctx), at_night, "{}", minute);
+            assert_eq!(engine.matches(&day, &ctx), at_day, "{}", minute);
+        }
+    }
+    #[test]
+    fn cpu_load_window() {
+        let mut engine = RuleEngine::new();
+        let busy = Condition::CpuLoadAbove {
+            load: 80.0,
+            secs: 60,
+        };
+        //样本还未覆盖整个窗口
+        for t in (0..60).step_by(10) {
+            engine.observe(&context(t, 0, 90.0));
+        }
+        assert!(!engine.matches(&busy, &context(50, 0, 90.0)));
+        engine.observe(&context(60, 0, 90.0));
+        assert!(engine.matches(&busy, &context(60, 0, 90.0)));
+        //窗口内有一个低于阈值的样本
+        engine.observe(&context(70, 0, 50.0));
+        assert!(!engine.matches(&busy, &context(70, 0, 50.0)));
+        for t in (80..=130).step_by(10) {
+            engine.observe(&context(t, 0, 90.0));
+        }
+        assert!(!engine.matches(&busy, &context(130, 0, 90.0)));
+        engine.observe(&context(140, 0, 90.0));
+        assert!(engine.matches(&busy, &context(140, 0, 90.0)));
+        //超过最长窗口的样本被丢弃
+        engine.observe(&context(140 + MAX_LOAD_WINDOW_SECS + 1, 0, 90.0));
+        assert_eq!(engine.load_samples.len(), 1);
+    }
+    #[test]
+    fn edge_triggering() {
+        let mut engine = RuleEngine::new();
+        let rules = [rule(
+            "low",
+            vec![Condition::BatteryBelow { percentage: 20.0 }],
+        )];
+        let mut ctx = context(0, 0, 0.0);
+        ctx.percentage = Some(15.0);
+        assert_eq!(engine.triggered(&rules, &ctx).len(), 1);
+        //持续成立时不重复触发
+        assert!(engine.triggered(&rules, &ctx).is_empty());
+        ctx.percentage = Some(25.0);
+        assert!(engine.triggered(&rules, &ctx).is_empty());
+        ctx.percentage = Some(15.0);
+        assert_eq!(engine.triggered(&rules, &ctx).len(), 1);
+    }
+    #[test]
+    fn validate_rules() {
+        let ok = rule(
+            "ok",
+            vec![Condition::CpuLoadAbove {
+                load: 80.0,
+                secs: 3600,
+            }],
+        );
+        assert!(validate(&[ok.clone()]).is_ok());
+        assert!(validate(&[ok.clone(), ok]).is_err());
+        let too_long = rule(
+            "long",
+            vec![Condition::CpuLoadAbove {
+                load: 80.0,
+                secs: 3601,
+            }],
+        );
+        assert!(validate(&[too_long]).is_err());
+        let bad_time = rule(
+            "time",
+            vec![Condition::TimeBetween {
+                start: 0,
+                end: 1440,
+            }],
+        );
+        assert!(validate(&[bad_time]).is_err());
+        assert!(validate(&[rule("empty", vec![])]).is_err());
+    }
+}
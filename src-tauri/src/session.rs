@@ -1,9 +1,13 @@
 use crate::config;
+use crate::experiment;
+use crate::rules;
 use crate::windows;
 use chrono::prelude::*;
+use log::{log, Level};
 use serde::Deserialize;
 use serde::Serialize;
 use status::{Last, Status};
+use std::collections::VecDeque;
 use tauri::AppHandle;
 use tauri::Emitter;
 #[derive(Debug, Default, Deserialize, Serialize)]
@@ -32,6 +36,12 @@ impl EventChannel {
             handler.emit("history_info_updated", true).unwrap();
         }
     }
+    pub fn emit_rule_notification(handler: &AppHandle, rule: &str, message: &str) {
+        handler.emit("rule_notification", (rule, message)).unwrap();
+    }
+    pub fn emit_health_alert(handler: &AppHandle, message: &str) {
+        handler.emit("health_alert", message).unwrap();
+    }
     pub fn emit_ui_update(handler: &AppHandle, current: &SessionState) {
         if current.is_min_tray {
             return;
@@ -52,10 +62,18 @@ impl EventChannel {
     }
 }
 
+//persis不可用时最多缓存的功率修改记录
+const MAX_PENDING_AUDITS: usize = 100;
+//设置功率后需稳定运行的秒数，之后才认为该限制是安全的
+const POWER_GUARD_SECS: i64 = 120;
 pub struct PowerLock {
     pub limit: power::PowerLimit,
     pub enable: bool,
     pub lastcheck: i64,
+    //上次会话崩溃导致跳过恢复时的提示
+    pub warning: Option<String>,
+    //最近一次设置功率的时间，超过POWER_GUARD_SECS后清除标记
+    pub guard_at: Option<i64>,
 }
 impl Default for PowerLock {
     fn default() -> Self {
@@ -63,6 +81,8 @@ impl Default for PowerLock {
             limit: Default::default(),
             enable: false,
             lastcheck: Utc::now().timestamp(),
+            warning: None,
+            guard_at: None,
         }
     }
 }
@@ -80,8 +100,24 @@ pub struct SessionState {
     pub system: system::Status,
     pub power: Option<power::Status>,
     pub power_lock: PowerLock,
+    //闭环调节，启用时不使用功率锁定
+    pub governor: Option<power::Governor>,
+    pub governor_output: Option<power::GovernorOutput>,
+    //预设对比实验，运行时不使用功率锁定和闭环调节
+    pub experiment: Option<experiment::Experiment>,
+    pub experiment_report: Option<persis::ExperimentReport>,
+    //首次初始化时记录的出厂功率
+    pub factory_limit: Option<power::FactoryLimit>,
+    pub rule_engine: rules::RuleEngine,
+    //待写入persis的功率修改记录
+    pub power_audits: VecDeque<persis::PowerAudit>,
     pub channel: EventChannel,
     pub persis: Option<persis::Manager>,
+    //正在退出，不再重新打开persis
+    pub exiting: bool,
+    pub sleep_detector: persis::SleepDetector,
+    //最近一次计算综合健康度的日期
+    health_day: i64,
 }
 impl SessionState {
     pub fn new(config: config::Config) -> Self {
@@ -89,16 +125,25 @@ impl SessionState {
         let mut battery: Option<battery::Status> = None;
         if let Some(rows) = battery::Status::build() {
             if rows.len() > 0 {
-                battery = Some(rows[0].clone());
+                let mut status = rows[0].clone();
+                status.set_debounce(config.debounce);
+                battery = Some(status);
             }
         }
         let is_admin = windows::is_admin();
-        Self {
+        let mut session = Self {
             is_admin,
             is_min_tray: false,
             config,
             battery,
             power_lock: PowerLock::new(),
+            governor: None,
+            governor_output: None,
+            experiment: None,
+            experiment_report: None,
+            factory_limit: None,
+            rule_engine: rules::RuleEngine::new(),
+            power_audits: VecDeque::new(),
             channel: EventChannel::new(),
             power: match system.support_power_set && is_admin {
                 true => match power::Status::build() {
@@ -118,6 +163,405 @@ impl SessionState {
             },
             system,
             persis: None,
+            exiting: false,
+            sleep_detector: persis::SleepDetector::default(),
+            health_day: 0,
+        };
+        session.init_factory_limit();
+        session.init_power_presets();
+        session.restore_power_lock();
+        if session.config.governor.enable && !session.power_lock.enable {
+            session.governor = Some(power::Governor::new(session.config.governor));
+        }
+        session
+    }
+    //恢复上次保存的锁定状态，实际设置在服务循环中power可用后进行
+    fn restore_power_lock(&mut self) {
+        let guard = config::load_power_guard();
+        config::clear_power_guard();
+        let saved = self.config.power_lock.clone();
+        if !saved.enable {
+            return;
+        }
+        if let Some(guard) = guard {
+            let msg = format!(
+                "The previous session exited unexpectedly within {}s after applying power limit(stapm:{},slow:{},fast:{}) at {}, power lock was disabled.",
+                POWER_GUARD_SECS,
+                guard.limit.stapm_limit,
+                guard.limit.slow_limit,
+                guard.limit.fast_limit,
+                DateTime::<Utc>::from_timestamp(guard.applied_at, 0).unwrap_or_default()
+            );
+            log!(Level::Warn, "{}", msg);
+            self.power_lock.warning = Some(msg);
+            self.set_power_lock(false, saved.limit, saved.preset);
+            return;
+        }
+        self.power_lock.enable = true;
+        self.power_lock.limit = saved.limit;
+        self.power_lock.lastcheck = 0;
+        self.set_active_preset(saved.preset);
+    }
+    //修改锁定状态并保存到配置
+    pub fn set_power_lock(
+        &mut self,
+        enable: bool,
+        limit: power::PowerLimit,
+        preset: Option<String>,
+    ) {
+        //先结束实验，实验恢复的状态会被本次设置覆盖
+        if enable {
+            self.stop_experiment();
+        }
+        self.power_lock.enable = enable;
+        self.power_lock.limit = limit;
+        if enable {
+            self.set_active_preset(preset.clone());
+            if self.governor.is_some() {
+                self.governor = None;
+                self.governor_output = None;
+                self.config.governor.enable = false;
+            }
+        }
+        self.config.power_lock = config::PowerLockConfig {
+            enable,
+            limit,
+            preset,
+        };
+        if let Err(e) = config::save_config(&self.config) {
+            log!(Level::Error, "save power lock err:{}", e);
+        }
+    }
+    //修改闭环调节配置并保存，启用时关闭功率锁定
+    pub fn set_governor(&mut self, governor: power::GovernorConfig) {
+        if governor.enable {
+            self.stop_experiment();
+            if self.power_lock.enable {
+                let saved = self.config.power_lock.clone();
+                self.set_power_lock(false, saved.limit, saved.preset);
+            }
+            self.set_active_preset(None);
+            self.governor = Some(power::Governor::new(governor));
+        } else {
+            self.governor = None;
+        }
+        self.governor_output = None;
+        self.config.governor = governor;
+        if let Err(e) = config::save_config(&self.config) {
+            log!(Level::Error, "save governor err:{}", e);
+        }
+    }
+    pub fn stop_experiment(&mut self) {
+        if let Some(v) = self.experiment.take() {
+            log!(Level::Warn, "experiment stopped");
+            experiment::stop(self, v);
+        }
+    }
+    fn governor_input(&self) -> power::GovernorInput {
+        power::GovernorInput {
+            temperature: self.power.as_ref().and_then(|x| x.tctl_temp_value),
+            //energy_rate放电时为负
+            drain: self.battery.as_ref().map(|x| (-x.energy_rate).max(0.0)),
+        }
+    }
+    //每个服务循环调用一次，功率变化超过0.5W时才实际设置
+    pub fn tick_governor(&mut self, now: i64) {
+        if !self.is_admin || !self.system.support_power_set || self.power.is_none() {
+            return;
+        }
+        let input = self.governor_input();
+        let output = match self.governor.as_mut() {
+            Some(governor) => governor.tick(now, &input),
+            None => return,
+        };
+        self.governor_output = Some(output);
+        if output.fallback {
+            log!(
+                Level::Warn,
+                "governor reading missing, fallback to safe limit"
+            );
+        }
+        let info = self.power.as_ref().unwrap();
+        if (info.stapm_limit - output.limit.stapm_limit).abs() < 0.5
+            && (info.slow_limit - output.limit.slow_limit).abs() < 0.5
+            && (info.fast_limit - output.limit.fast_limit).abs() < 0.5
+        {
+            return;
+        }
+        if let Err(e) =
+            self.apply_power_limit(&output.limit, persis::PowerAuditSource::Governor, None)
+        {
+            log!(Level::Error, "governor set_limit err:{}", e);
+            let mut governor = self.config.governor;
+            governor.enable = false;
+            self.set_governor(governor);
+        }
+    }
+    //功率设置后稳定运行足够久，删除崩溃标记
+    pub fn check_power_guard(&mut self, now: i64) {
+        if let Some(at) = self.power_lock.guard_at {
+            if now - at > POWER_GUARD_SECS {
+                config::clear_power_guard();
+                self.power_lock.guard_at = None;
+            }
+        }
+    }
+    fn init_factory_limit(&mut self) {
+        if let Some(power) = &self.power {
+            let path = config::get_exe_directory().join("power_factory.json");
+            match power::FactoryLimit::load_or_capture(&path, power) {
+                Ok(factory) => self.factory_limit = Some(factory),
+                Err(e) => log!(Level::Error, "load factory power limit err:{}", e),
+            }
+        }
+    }
+    //恢复出厂功率，同时解除锁定
+    pub fn restore_power_limit(
+        &mut self,
+    ) -> Result<(bool, Option<power::Status>), power::LimitError> {
+        if !self.is_admin || !self.system.support_power_set || self.power.is_none() {
+            return Ok((false, None));
+        }
+        let factory = match &self.factory_limit {
+            Some(v) => v.clone(),
+            None => return Ok((false, None)),
+        };
+        if self.power_lock.enable {
+            let saved = self.config.power_lock.clone();
+            self.set_power_lock(false, saved.limit, saved.preset);
+        }
+        let mut audit =
+            self.new_power_audit(persis::PowerAuditSource::Restore, None, &factory.limit);
+        if let Err(e) = power::restore_factory(&factory) {
+            audit.error = Some(e.to_string());
+            self.push_power_audit(audit);
+            return Err(e);
+        }
+        config::clear_power_guard();
+        self.power_lock.guard_at = None;
+        self.set_active_preset(None);
+        let (applied, info) = self.read_back_power(&factory.limit, audit);
+        log!(
+            Level::Info,
+            "restore factory power limit applied:{}",
+            applied
+        );
+        Ok((applied, Some(info)))
+    }
+    //首次检测到cpu_family时，根据出厂功率生成默认预设
+    fn init_power_presets(&mut self) {
+        if !self.config.power_presets.is_empty() {
+            return;
+        }
+        if let Some(power) = &self.power {
+            self.config.power_presets = power::default_presets(power.identifier.cpu_family);
+            if !self.config.power_presets.is_empty() {
+                if let Err(e) = config::save_config(&self.config) {
+                    log!(Level::Error, "save default power presets err:{}", e);
+                }
+            }
+        }
+    }
+    //设置功率限制并回读，返回是否全部生效
+    pub fn apply_power_limit(
+        &mut self,
+        limit: &power::PowerLimit,
+        source: persis::PowerAuditSource,
+        detail: Option<String>,
+    ) -> Result<(bool, Option<power::Status>), power::LimitError> {
+        if !self.is_admin || !self.system.support_power_set || self.power.is_none() {
+            return Ok((false, None));
+        }
+        let mut audit = self.new_power_audit(source, detail, limit);
+        if let Err(e) = power::set_limit(limit) {
+            audit.error = Some(e.to_string());
+            self.push_power_audit(audit);
+            return Err(e);
+        }
+        let now = Utc::now().timestamp();
+        if let Err(e) = config::save_power_guard(&config::PowerGuard {
+            applied_at: now,
+            limit: *limit,
+        }) {
+            log!(Level::Error, "save power guard err:{}", e);
+        }
+        self.power_lock.guard_at = Some(now);
+        let (applied, info) = self.read_back_power(limit, audit);
+        Ok((applied, Some(info)))
+    }
+    //记录设置前的功率，调用前需确认power可用
+    fn new_power_audit(
+        &mut self,
+        source: persis::PowerAuditSource,
+        detail: Option<String>,
+        limit: &power::PowerLimit,
+    ) -> persis::PowerAudit {
+        let info = self.power.as_mut().unwrap();
+        info.last();
+        persis::PowerAudit {
+            timestamp: Utc::now().timestamp_millis(),
+            source,
+            detail,
+            before: info.current_limit(),
+            request: *limit,
+            after: None,
+            success: false,
+            error: None,
+        }
+    }
+    //回读设置后的功率并记录
+    fn read_back_power(
+        &mut self,
+        limit: &power::PowerLimit,
+        mut audit: persis::PowerAudit,
+    ) -> (bool, power::Status) {
+        let info = self.power.as_mut().unwrap();
+        info.last();
+        let applied = info.stapm_limit == limit.stapm_limit
+            && info.slow_limit == limit.slow_limit
+            && info.fast_limit == limit.fast_limit;
+        let info = info.clone();
+        audit.after = Some(info.current_limit());
+        audit.success = applied;
+        self.push_power_audit(audit);
+        (applied, info)
+    }
+    fn push_power_audit(&mut self, audit: persis::PowerAudit) {
+        if !audit.success {
+            log!(Level::Warn, "power limit not applied:{:?}", audit);
+        }
+        self.power_audits.push_back(audit);
+        while self.power_audits.len() > MAX_PENDING_AUDITS {
+            self.power_audits.pop_front();
+        }
+    }
+    //将缓存的功率修改记录写入persis
+    pub async fn flush_power_audit(&mut self) {
+        if let Some(manager) = &self.persis {
+            while let Some(audit) = self.power_audits.pop_front() {
+                if let Err(e) = manager.insert_power_audit(&audit).await {
+                    log!(Level::Error, "insert power audit err:{}", e);
+                    self.power_audits.push_front(audit);
+                    break;
+                }
+            }
+        }
+    }
+    //所有退出路径共用，按配置恢复出厂功率后关闭persis，重复调用时不再执行
+    pub async fn prepare_exit(&mut self) {
+        if self.exiting {
+            return;
+        }
+        if self.config.restore_on_exit {
+            if let Err(e) = self.restore_power_limit() {
+                log!(Level::Error, "restore power limit err:{}", e);
+            }
+        }
+        self.shutdown().await;
+    }
+    //退出前保存缓冲的数据并关闭数据库，之后不再写入persis
+    pub async fn shutdown(&mut self) {
+        self.exiting = true;
+        self.flush_power_audit().await;
+        if let Some(mut manager) = self.persis.take() {
+            if let Err(e) = manager.flush().await {
+                log!(Level::Error, "persis flush err:{}", e);
+            }
+            manager.close().await;
+        }
+    }
+    //刷新电池读数，刷新前检测睡眠，检测到时记录睡眠前后的读数
+    pub async fn refresh_battery(&mut self) {
+        let sleep = self.sleep_detector.poll(Utc::now().timestamp_millis());
+        let before = sleep.and(self.battery.clone());
+        if let Some(battery) = self.battery.as_mut() {
+            battery.last();
+        }
+        if let (Some((start_at, end_at)), Some(before), Some(after)) =
+            (sleep, before, self.battery.as_ref())
+        {
+            let session = persis::SleepSession {
+                start_at,
+                end_at,
+                start_percentage: before.percentage,
+                end_percentage: after.percentage,
+                start_capacity: before.capacity,
+                end_capacity: after.capacity,
+                full_capacity: after.full_capacity,
+            };
+            log!(
+                Level::Warn,
+                "resume from sleep of {:.2}h, drain {:.2}W",
+                session.hours(),
+                session.drain_rate()
+            );
+            if let Some(manager) = &self.persis {
+                if let Err(e) = manager.insert_sleep_session(&session).await {
+                    log!(Level::Error, "insert sleep session err:{}", e);
+                }
+            }
+        }
+    }
+    //每天计算一次综合健康度，越过里程碑或健康度阈值时提醒
+    pub async fn check_health(&mut self, handler: &AppHandle) {
+        let day = persis::health_day(Utc::now().timestamp_millis());
+        if self.health_day == day {
+            return;
+        }
+        let (Some(battery), Some(manager)) = (&self.battery, &self.persis) else {
+            return;
+        };
+        match manager.update_health(battery).await {
+            Ok(update) => {
+                self.health_day = day;
+                let Some(update) = update else {
+                    return;
+                };
+                log!(
+                    Level::Warn,
+                    "battery health score {:.1} of day({})",
+                    update.current.score,
+                    day
+                );
+                let alert = &self.config.health_alert;
+                for message in update.alerts(&alert.score_milestones, &alert.soh_thresholds) {
+                    log!(Level::Warn, "{}", message);
+                    EventChannel::emit_health_alert(handler, &message);
+                }
+            }
+            Err(e) => log!(Level::Error, "update health err:{}", e),
+        }
+    }
+    //两次后台更新之间的额外采样，只刷新电池读数并写入persis，返回历史段是否变化
+    pub async fn sample_battery(&mut self) -> bool {
+        if self.persis.is_none() {
+            return false;
+        }
+        self.refresh_battery().await;
+        let (Some(battery), Some(manager)) = (self.battery.as_ref(), self.persis.as_mut()) else {
+            return false;
+        };
+        match manager
+            .insert_battery(battery, &self.system, |_| async {})
+            .await
+        {
+            Ok(res) => res.contains(&persis::InsertModifyed::BatteryHistory),
+            Err(e) => {
+                log!(Level::Error, "manager.insert_battery error:{}", e);
+                false
+            }
+        }
+    }
+    pub fn find_power_preset(&self, name: &str) -> Option<power::Preset> {
+        self.config
+            .power_presets
+            .iter()
+            .find(|x| x.name == name)
+            .cloned()
+    }
+    pub fn set_active_preset(&mut self, name: Option<String>) {
+        if let Some(info) = self.power.as_mut() {
+            info.active_preset = name;
         }
     }
 }
@@ -2,6 +2,7 @@ use crate::session;
 use crate::windows;
 use humantime::format_duration;
 use image::{ExtendedColorType, ImageBuffer, ImageEncoder, Rgb, Rgba};
+use log::{log, Level};
 use rusttype::{Font, Scale};
 use std::{error::Error, sync::Arc};
 use tauri::{
@@ -138,13 +139,44 @@ pub fn build(app: &App, id: &str) {
     let admin_i =
         MenuItem::with_id(app, "admin", "Run at Administrator", true, None::<&str>).unwrap();
 
-    let menu = Menu::with_items(app, &[&admin_i, &quit_i]).unwrap();
+    let restore_i = MenuItem::with_id(
+        app,
+        "restore_power",
+        "Restore Default Power",
+        true,
+        None::<&str>,
+    )
+    .unwrap();
+
+    let menu = Menu::with_items(app, &[&admin_i, &restore_i, &quit_i]).unwrap();
     TrayIconBuilder::with_id(id)
         .menu(&menu)
         .show_menu_on_left_click(false)
         .on_menu_event(|app, event| match event.id.as_ref() {
             "quit" => {
-                app.exit(0);
+                let state = app.state::<Arc<Mutex<session::SessionState>>>();
+                tokio::spawn({
+                    let state = Arc::clone(&state);
+                    let app = app.clone();
+                    async move {
+                        let mut state = state.lock().await;
+                        state.prepare_exit().await;
+                        app.exit(0);
+                    }
+                });
+            }
+            "restore_power" => {
+                let state = app.state::<Arc<Mutex<session::SessionState>>>();
+                tokio::spawn({
+                    let state = Arc::clone(&state);
+                    async move {
+                        let mut state = state.lock().await;
+                        if let Err(e) = state.restore_power_limit() {
+                            log!(Level::Error, "restore power limit err:{}", e);
+                        }
+                        state.flush_power_audit().await;
+                    }
+                });
             }
             "admin" => {
                 let state = app.state::<Arc<Mutex<session::SessionState>>>();
@@ -75,6 +75,17 @@ pub fn elevate_self() {
 }
 #[cfg(not(windows))]
 pub fn elevate_self() {}
+//release版本没有控制台，命令行调用时附加到父进程的控制台以输出结果
+#[cfg(windows)]
+pub fn attach_console() {
+    use winapi::um::wincon::{AttachConsole, ATTACH_PARENT_PROCESS};
+
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+#[cfg(not(windows))]
+pub fn attach_console() {}
 pub fn active_window(app: &AppHandle, name: &str) {
     if let Some(window) = app.get_webview_window(name) {
         if window.is_minimized().unwrap() {
@@ -11,6 +11,15 @@ export interface FormValue extends LimitSet {
   auto_lock: boolean;
   modifyed: boolean;
 }
+export interface Preset {
+  name: string;
+  limit: LimitSet;
+}
+export interface PowerLockConfig {
+  enable: boolean;
+  limit: LimitSet;
+  preset: string | null;
+}
 export interface PowerInfo extends LimitSet {
   identifier: {
     cpu_family: number;
@@ -19,11 +28,117 @@ export interface PowerInfo extends LimitSet {
   stamp_value: number;
   fast_value: number;
   slow_value: number;
+  active_preset: string | null;
+}
+export interface PowerPoint extends LimitSet {
+  timestamp: number;
+  stapm_value: number;
+  slow_value: number;
+  fast_value: number;
+}
+export interface PowerAudit {
+  id: number;
+  timestamp: number;
+  source:
+    | "user"
+    | "power_lock"
+    | "rule"
+    | "restore"
+    | "governor"
+    | "experiment";
+  detail: string | null;
+  before_stapm: number;
+  before_slow: number;
+  before_fast: number;
+  request_stapm: number;
+  request_slow: number;
+  request_fast: number;
+  after_stapm: number | null;
+  after_slow: number | null;
+  after_fast: number | null;
+  success: boolean;
+  error: string | null;
+  // 完整的功率设置，包括扩展参数，旧记录为null
+  before_limit: LimitSet | null;
+  request_limit: LimitSet | null;
+  after_limit: LimitSet | null;
+}
+export interface GovernorConfig {
+  enable: boolean;
+  target:
+    | { kind: "temperature"; celsius: number }
+    | { kind: "drain"; watts: number };
+  min_limit: number;
+  max_limit: number;
+  fast_limit: number;
+  safe_limit: number;
+  kp: number;
+  ki: number;
+  kd: number;
+  max_step: number;
+}
+export interface GovernorOutput {
+  limit: LimitSet;
+  measured: number | null;
+  fallback: boolean;
+}
+export interface PowerRecommendation {
+  limit: LimitSet;
+  target_hours: number;
+  predicted_hours: number | null;
+  confidence: number;
+  typical_load: number;
+}
+export interface ExperimentPlan {
+  presets: string[];
+  interval_secs: number;
+  rounds: number;
+}
+export interface MetricStats {
+  samples: number;
+  mean: number;
+  std_dev: number;
+  ci_low: number;
+  ci_high: number;
+}
+export interface ExperimentReport {
+  started_at: number;
+  ended_at: number;
+  interval_secs: number;
+  rounds: number;
+  summaries: {
+    preset: string;
+    metric: "drain" | "temperature" | "cpu_load";
+    stats: MetricStats;
+  }[];
+}
+export interface ExperimentProgress {
+  plan: ExperimentPlan;
+  started_at: number;
+  phase: number;
+  total_phases: number;
+  preset: string;
+  phase_started_at: number;
+}
+export interface Range {
+  min: number;
+  max: number;
+}
+export interface LimitRange {
+  cpu_family: number;
+  stapm_limit: Range;
+  slow_limit: Range;
+  fast_limit: Range;
+}
+export interface LimitError {
+  kind: "not_support" | "invalid";
+  violations?: { kind: string; field?: string; lower?: string; upper?: string }[];
 }
 export interface ApuPower extends PowerInfo {
   isAdmin: boolean;
   form_value: FormValue;
   init_value: LimitSet | undefined;
+  range: LimitRange | undefined;
 }
 let listenHandle: Promise<UnlistenFn>;
 export const useStore = defineStore("ApuPower", {
@@ -37,6 +152,7 @@ export const useStore = defineStore("ApuPower", {
         fast_limit: 15,
       },
       init_value: undefined,
+      range: undefined,
       isAdmin: false,
       identifier: { cpu_family: 0 },
       table: 0,
@@ -46,6 +162,7 @@ export const useStore = defineStore("ApuPower", {
       fast_value: 0,
       slow_limit: 0,
       slow_value: 0,
+      active_preset: null,
     };
   },
   getters: {},
@@ -71,6 +188,11 @@ export const useStore = defineStore("ApuPower", {
       if (this.isAdmin) {
         const info = (await invoke("get_powerinfo")) as PowerInfo;
         this.$patch(info);
+        if (!this.range) {
+          this.range = (await invoke("get_power_limit_range")) as
+            | LimitRange
+            | undefined;
+        }
       }
     },
     async update(nVal: PowerInfo) {
@@ -78,14 +200,103 @@ export const useStore = defineStore("ApuPower", {
     },
     async set_limit(val: LimitSet) {
       if (this.isAdmin) {
-        let result = (await invoke("set_power_limit", { limit: val })) as [
+        try {
+          let result = (await invoke("set_power_limit", { limit: val })) as [
+            boolean,
+            PowerInfo,
+          ];
+          debug(`invoke:set_power_limit-${JSON.stringify(result)}`);
+          return result;
+        } catch (e) {
+          warn(`invoke:set_power_limit rejected-${JSON.stringify(e)}`);
+          throw e as LimitError;
+        }
+      }
+    },
+    async restore_default() {
+      if (this.isAdmin) {
+        let result = (await invoke("restore_power_limit")) as [
+          boolean,
+          PowerInfo | null,
+        ];
+        debug(`invoke:restore_power_limit-${JSON.stringify(result)}`);
+        return result;
+      }
+    },
+    async get_history(start: number, end: number) {
+      return await invoke<PowerPoint[] | null>("get_power_history", {
+        start,
+        end,
+      });
+    },
+    async get_audit_page(cursor: number | null, size: number) {
+      return await invoke<PowerAudit[] | null>("get_power_audit_page", {
+        cursor,
+        size,
+      });
+    },
+    // 第二项为上次异常退出后自动解除锁定的提示
+    async get_power_lock() {
+      return await invoke<[PowerLockConfig, string | null]>("get_power_lock");
+    },
+    async get_governor() {
+      return await invoke<[GovernorConfig, GovernorOutput | null]>(
+        "get_governor"
+      );
+    },
+    async set_governor(governor: GovernorConfig) {
+      if (this.isAdmin) {
+        try {
+          return await invoke<boolean>("set_governor", { governor });
+        } catch (e) {
+          warn(`invoke:set_governor rejected-${JSON.stringify(e)}`);
+          throw e as LimitError;
+        }
+      }
+    },
+    async recommend(hours: number) {
+      return await invoke<PowerRecommendation | null>(
+        "recommend_power_limit",
+        { hours }
+      );
+    },
+    async start_experiment(plan: ExperimentPlan) {
+      if (this.isAdmin) {
+        return await invoke<boolean>("start_experiment", { plan });
+      }
+    },
+    async stop_experiment() {
+      return await invoke<boolean>("stop_experiment");
+    },
+    async get_experiment() {
+      return await invoke<
+        [ExperimentProgress | null, ExperimentReport | null]
+      >("get_experiment");
+    },
+    async get_presets() {
+      return (await invoke("get_power_presets")) as Preset[];
+    },
+    async save_preset(preset: Preset) {
+      return (await invoke("save_power_preset", { preset })) as boolean;
+    },
+    async delete_preset(name: string) {
+      return (await invoke("delete_power_preset", { name })) as boolean;
+    },
+    async apply_preset(name: string) {
+      if (this.isAdmin) {
+        let result = (await invoke("apply_power_preset", { name })) as [
           boolean,
-          PowerInfo,
+          PowerInfo | null,
         ];
-        debug(`invoke:set_power_limit-${JSON.stringify(result)}`);
+        debug(`invoke:apply_power_preset(${name})-${JSON.stringify(result)}`);
         return result;
       }
     },
+    async lock_preset(name: string, lock: boolean) {
+      if (this.isAdmin) {
+        return (await invoke("lock_power_preset", { name, lock })) as boolean;
+      }
+    },
     async set_limit_lock(enable: boolean, val: LimitSet) {
       if (this.isAdmin) {
         let result = (await invoke("set_power_limit_lock", {
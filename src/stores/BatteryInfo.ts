@@ -10,6 +10,7 @@ export interface BatteryInfo {
   state_changed: boolean;
   timestamp: number;
   state: string;
+  raw_state: string;
   percentage: number;
   energy_rate: number;
   capacity: number;
@@ -19,6 +20,47 @@ export interface BatteryInfo {
   voltage: number;
   time_to_empty_secs: number;
   time_to_full_secs: number;
+  cycle_count?: number;
+}
+//驱动报告的循环次数与按放电量折算的软件计数
+export interface CycleHealth {
+  battery_key: string;
+  driver_cycle_count?: number;
+  software_cycles: number;
+  discharged_wh: number;
+  effective_cycles: number;
+  state_of_health: number;
+  wear_per_100_cycles?: number;
+}
+export interface StressBin {
+  soc_band: number;
+  temp_band: number;
+  soc_label: string;
+  temp_label: string;
+  secs: number;
+}
+//电量/温度区间的停留时长，aging_rate为相对于50%电量、25°C的日历老化速率
+export interface StressHistogram {
+  bins: StressBin[];
+  soc_secs: number[];
+  temp_secs: number[];
+  total_secs: number;
+  aging_rate: number;
+  stress_score: number;
+  high_soc_hot_share: number;
+  updated_at: number;
+}
+//每天计算一次的综合健康度(0-100)
+export interface HealthRecord {
+  id: number;
+  battery_key: string;
+  day: number;
+  score: number;
+  state_of_health: number;
+  fade_per_year?: number;
+  cycles?: number;
+  stress_score?: number;
+  anomalies: number;
 }
 let listenHandle: Promise<UnlistenFn>;
 export const useStore = defineStore("BatteryInfo", {
@@ -31,6 +73,7 @@ export const useStore = defineStore("BatteryInfo", {
     state_changed: false,
     timestamp: 0,
     state: "",
+    raw_state: "",
     percentage: 0,
     energy_rate: 0,
     capacity: 0,
@@ -53,5 +96,20 @@ export const useStore = defineStore("BatteryInfo", {
     async update(nVal: BatteryInfo) {
       this.$patch(nVal);
     },
+    async cycles() {
+      return await invoke<CycleHealth | null>("get_battery_cycles");
+    },
+    async health(size: number) {
+      return await invoke<HealthRecord[] | null>("get_battery_health", {
+        size,
+      });
+    },
+    async stress() {
+      return await invoke<StressHistogram | null>("get_battery_stress");
+    },
+    // 扩展名为.md时写出Markdown，其余为HTML
+    async writeReport(path: string) {
+      return await invoke<void>("write_battery_report", { path });
+    },
   },
 });
@@ -6,6 +6,26 @@ export interface Config {
   ui_update: number;
   service_update: number;
   record_battery_history: boolean;
+  restore_on_exit: boolean;
+  sample_interval_ms: number;
+}
+export interface RetentionPolicy {
+  memory_secs: number;
+  realtime_secs: number;
+  one_minute_secs: number;
+  one_hour_secs: number;
+  one_day_secs: number | null;
+}
+//电池状态切换的去抖参数
+export interface DebounceConfig {
+  min_dwell_ms: number;
+  ignore_unknown: boolean;
+  merge_full_above: number | null;
+}
+//综合健康度(0-100)和电池健康度(0-1)降到阈值以下时提醒
+export interface HealthAlertConfig {
+  score_milestones: number[];
+  soh_thresholds: number[];
 }
 export const useStore = defineStore("Config", {
   state: (): Config => {
@@ -15,6 +35,8 @@ export const useStore = defineStore("Config", {
       ui_update: 2,
       service_update: 1,
       record_battery_history: true,
+      restore_on_exit: false,
+      sample_interval_ms: 0,
     };
   },
   getters: {},
@@ -24,6 +46,24 @@ export const useStore = defineStore("Config", {
       this.$patch(cfg);
       return cfg;
     },
+    async get_retention() {
+      return await invoke<RetentionPolicy>("get_retention_policy");
+    },
+    async set_retention(retention: RetentionPolicy) {
+      return await invoke<boolean>("set_retention_policy", { retention });
+    },
+    async get_debounce() {
+      return await invoke<DebounceConfig>("get_battery_debounce");
+    },
+    async set_debounce(debounce: DebounceConfig) {
+      return await invoke<boolean>("set_battery_debounce", { debounce });
+    },
+    async get_health_alert() {
+      return await invoke<HealthAlertConfig>("get_health_alert");
+    },
+    async set_health_alert(alert: HealthAlertConfig) {
+      return await invoke<boolean>("set_health_alert", { alert });
+    },
     async update(nVal: Config) {
       this.$patch(nVal);
       await invoke("set_config", {
@@ -3,6 +3,7 @@ import { invoke } from "@tauri-apps/api/core";
 import { listen, UnlistenFn } from "@tauri-apps/api/event";
 import { warn, debug, error } from "@tauri-apps/plugin-log";
 export interface HistoryInfo {
+  id: number;
   prev_timestamp?: number;
   timestamp_diff?: number;
   prev_state_of_health?: number;
@@ -24,6 +25,55 @@ export interface HistoryInfo {
   energy_rate: number;
   voltage: number;
   cpu_load: number;
+  temperature?: number;
+  cycle_count?: number;
+  time_to_empty_secs?: number;
+  time_to_full_secs?: number;
+}
+//小时或天的汇总数据
+export interface BatteryRollup {
+  timestamp: number;
+  state: string;
+  samples: number;
+  percentage_min: number;
+  percentage_max: number;
+  percentage_avg: number;
+  percentage_last: number;
+  energy_rate_min: number;
+  energy_rate_max: number;
+  energy_rate_avg: number;
+  energy_rate_last: number;
+  voltage_min: number;
+  voltage_max: number;
+  voltage_avg: number;
+  voltage_last: number;
+  cpu_load_min: number;
+  cpu_load_max: number;
+  cpu_load_avg: number;
+  cpu_load_last: number;
+}
+export type SeriesMetric = "percentage" | "energy_rate" | "voltage" | "cpu_load";
+export type SeriesPoint =
+  | { kind: "value"; timestamp: number; values: (number | null)[] }
+  | { kind: "gap"; start: number; end: number };
+export interface Series {
+  metrics: SeriesMetric[];
+  source: string;
+  interval_ms: number;
+  points: SeriesPoint[];
+}
+//一次睡眠前后的电池读数，drain_rate为平均放电功率(W)，percentage_rate为每小时消耗的电量(0-1)
+export interface SleepSession {
+  id: number;
+  start_at: number;
+  end_at: number;
+  start_percentage: number;
+  end_percentage: number;
+  start_capacity: number;
+  end_capacity: number;
+  full_capacity: number;
+  drain_rate: number;
+  percentage_rate: number;
 }
 let listenHandle: Promise<UnlistenFn>;
 export const useStore = defineStore("HistoryInfo", {
@@ -45,5 +95,36 @@ export const useStore = defineStore("HistoryInfo", {
     history: async (id: number) => {
       return await invoke<HistoryInfo>("get_battery_history", { id });
     },
+    //合并状态抖动产生的短历史段，返回删除的段数
+    merge_micro_segments: async (minDurationMs?: number) => {
+      return await invoke<number | null>("merge_battery_history", {
+        minDurationMs,
+      });
+    },
+    series: async (
+      metrics: SeriesMetric[],
+      start: number,
+      end: number,
+      maxPoints?: number,
+    ) => {
+      return await invoke<Series | null>("get_battery_series", {
+        metrics,
+        start,
+        end,
+        maxPoints: maxPoints ?? 500,
+      });
+    },
+    sleep_sessions: async (start: number, end: number) => {
+      return await invoke<SleepSession[] | null>("get_sleep_sessions", {
+        start,
+        end,
+      });
+    },
+    rollup: async (start: number, end: number) => {
+      return await invoke<BatteryRollup[] | null>("get_battery_rollup", {
+        start,
+        end,
+      });
+    },
   },
 });